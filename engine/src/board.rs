@@ -0,0 +1,230 @@
+use crate::{BOARD_HEIGHT, BOARD_WIDTH, BUFFER_HEIGHT};
+
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug)]
+pub enum Colors {
+    #[default]
+    Empty,
+    LightBlue,
+    Blue,
+    Orange,
+    Yellow,
+    Lime,
+    Purple,
+    Red,
+    /// Rows added to the bottom of the board rather than placed by the player
+    Garbage,
+}
+
+impl Colors {
+    pub fn is_empty(&self) -> bool {
+        matches!(self, Colors::Empty)
+    }
+}
+
+/// Cells of a board indexed by `[x][y]` with y pointing up
+pub type Cells = Vec<Vec<Colors>>;
+
+/// Rows removed by `Board::clear_lines`
+#[derive(Clone, Debug)]
+pub struct LineClear {
+    /// Indices of the cleared rows, lowest first
+    pub rows: Vec<usize>,
+    /// The board before the rows were cleared
+    pub before: Cells,
+}
+
+/// Grid of locked blocks. The tetromino in play is never stored here.
+#[derive(Clone, Debug)]
+pub struct Board {
+    width: usize,
+    height: usize,
+    cells: Cells,
+}
+
+/// A `BOARD_WIDTH` wide board with `BOARD_HEIGHT` visible rows under `BUFFER_HEIGHT` hidden
+/// ones
+impl Default for Board {
+    fn default() -> Self {
+        Board::new(BOARD_WIDTH, BOARD_HEIGHT + BUFFER_HEIGHT)
+    }
+}
+
+impl Board {
+    /// Empty board `width` cells across and `height` cells tall, counting any hidden rows
+    pub fn new(width: usize, height: usize) -> Board {
+        Board {
+            width,
+            height,
+            cells: vec![vec![Colors::Empty; height]; width],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn cells(&self) -> &Cells {
+        &self.cells
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Colors {
+        self.cells[x][y]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Colors) {
+        self.cells[x][y] = color;
+    }
+
+    /// Whether `(x, y)` is inside the board and empty
+    pub fn is_free(&self, x: i32, y: i32) -> bool {
+        x >= 0
+            && y >= 0
+            && x < self.width as i32
+            && y < self.height as i32
+            && self.cells[x as usize][y as usize].is_empty()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.iter().flatten().all(Colors::is_empty)
+    }
+
+    /// Number of rows containing garbage
+    pub fn garbage_rows(&self) -> usize {
+        (0..self.height)
+            .filter(|&y| self.cells.iter().any(|column| column[y] == Colors::Garbage))
+            .count()
+    }
+
+    /// Inserts `rows` (lowest first, each `width` cells long) at the bottom of the board,
+    /// shifting every other row up. Returns false if blocks were pushed off the top.
+    pub fn insert_rows(&mut self, rows: &[Vec<Colors>]) -> bool {
+        let count = rows.len().min(self.height);
+        let fits = self
+            .cells
+            .iter()
+            .all(|column| column[self.height - count..].iter().all(Colors::is_empty));
+
+        for (x, column) in self.cells.iter_mut().enumerate() {
+            column.copy_within(..self.height - count, count);
+            for (y, row) in rows[..count].iter().enumerate() {
+                column[y] = row[x];
+            }
+        }
+
+        fits
+    }
+
+    /// Indices of every full row, lowest first
+    pub fn full_rows(&self) -> Vec<usize> {
+        (0..self.height)
+            .filter(|&y| self.cells.iter().all(|column| !column[y].is_empty()))
+            .collect()
+    }
+
+    /// Removes every full row at once, moving the rows above down to fill the gaps
+    pub fn clear_lines(&mut self) -> LineClear {
+        let rows = self.full_rows();
+        let before = self.cells.clone();
+
+        if !rows.is_empty() {
+            let mut cleared = rows.iter().peekable();
+            let mut target = 0;
+
+            for y in 0..self.height {
+                if cleared.next_if_eq(&&y).is_some() {
+                    continue;
+                }
+                for (column, before) in self.cells.iter_mut().zip(&before) {
+                    column[target] = before[y];
+                }
+                target += 1;
+            }
+
+            for column in self.cells.iter_mut() {
+                column[target..].fill(Colors::Empty);
+            }
+        }
+
+        LineClear { rows, before }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill_row(board: &mut Board, y: usize, color: Colors) {
+        for x in 0..board.width() {
+            board.set(x, y, color);
+        }
+    }
+
+    #[test]
+    fn clear_lines_removes_every_full_row_at_once() {
+        let mut board = Board::new(4, 6);
+        fill_row(&mut board, 0, Colors::Red);
+        board.set(1, 1, Colors::Blue);
+        fill_row(&mut board, 2, Colors::Lime);
+        board.set(3, 3, Colors::Yellow);
+        let cells = board.cells().clone();
+
+        let clear = board.clear_lines();
+
+        assert_eq!(clear.rows, vec![0, 2]);
+        assert_eq!(clear.before, cells);
+        assert_eq!(board.get(1, 0), Colors::Blue);
+        assert_eq!(board.get(3, 1), Colors::Yellow);
+        assert_eq!(
+            board
+                .cells()
+                .iter()
+                .flatten()
+                .filter(|c| !c.is_empty())
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn clear_lines_without_full_rows_changes_nothing() {
+        let mut board = Board::new(4, 4);
+        board.set(0, 0, Colors::Red);
+        let cells = board.cells().clone();
+
+        let clear = board.clear_lines();
+
+        assert!(clear.rows.is_empty());
+        assert_eq!(board.cells(), &cells);
+    }
+
+    #[test]
+    fn insert_rows_shifts_rows_up() {
+        let mut board = Board::new(4, 4);
+        board.set(2, 0, Colors::Red);
+        let row = vec![
+            Colors::Garbage,
+            Colors::Empty,
+            Colors::Garbage,
+            Colors::Garbage,
+        ];
+
+        assert!(board.insert_rows(&[row.clone(), row]));
+        assert_eq!(board.get(2, 2), Colors::Red);
+        assert_eq!(board.get(1, 0), Colors::Empty);
+        assert_eq!(board.get(0, 1), Colors::Garbage);
+        assert_eq!(board.garbage_rows(), 2);
+    }
+
+    #[test]
+    fn insert_rows_reports_overflow() {
+        let mut board = Board::new(4, 4);
+        board.set(0, 3, Colors::Red);
+
+        assert!(!board.insert_rows(&[vec![Colors::Garbage; 4]]));
+        assert_eq!(board.garbage_rows(), 1);
+    }
+}
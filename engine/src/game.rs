@@ -0,0 +1,908 @@
+use crate::board::{Board, Cells, Colors};
+use crate::garbage::GarbageGenerator;
+use crate::generator::{GeneratorKind, PieceGenerator};
+use crate::gravity::Gravity;
+use crate::rotation::RotationSystem;
+use crate::scoring::{LockInfo, ScoringKind, ScoringRules, Spin};
+use crate::tetromino::{Piece, Tetrominos};
+use crate::{BOARD_HEIGHT, BOARD_WIDTH, BUFFER_HEIGHT};
+use std::collections::VecDeque;
+
+/// Waits before each tetromino spawns
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SpawnDelays {
+    /// Seconds between a tetromino locking and the next spawning (ARE)
+    pub entry: f32,
+    /// Extra seconds waited when the lock cleared lines
+    pub line_clear: f32,
+    /// Whether rotating or holding while waiting rotates or holds the tetromino as it spawns
+    /// (IRS and IHS)
+    pub initial_actions: bool,
+}
+
+impl Default for SpawnDelays {
+    fn default() -> Self {
+        SpawnDelays::NONE
+    }
+}
+
+impl SpawnDelays {
+    /// The next tetromino spawns as soon as the last locks
+    pub const NONE: SpawnDelays = SpawnDelays {
+        entry: 0.0,
+        line_clear: 0.0,
+        initial_actions: false,
+    };
+    /// Close to the NES - 10 frames of ARE and 20 more when clearing lines
+    pub const CLASSIC: SpawnDelays = SpawnDelays {
+        entry: 10.0 / 60.0,
+        line_clear: 20.0 / 60.0,
+        initial_actions: false,
+    };
+    /// Close to TGM - 30 frames of ARE, 41 more when clearing lines, with IRS and IHS
+    pub const TGM: SpawnDelays = SpawnDelays {
+        entry: 30.0 / 60.0,
+        line_clear: 41.0 / 60.0,
+        initial_actions: true,
+    };
+    pub const PRESETS: [SpawnDelays; 3] =
+        [SpawnDelays::NONE, SpawnDelays::CLASSIC, SpawnDelays::TGM];
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            SpawnDelays::NONE => "None",
+            SpawnDelays::CLASSIC => "Classic",
+            SpawnDelays::TGM => "TGM",
+            _ => "Custom",
+        }
+    }
+
+    pub fn next(&self) -> SpawnDelays {
+        let index = SpawnDelays::PRESETS
+            .iter()
+            .position(|delays| delays == self)
+            .map_or(0, |index| index + 1);
+        SpawnDelays::PRESETS[index % SpawnDelays::PRESETS.len()]
+    }
+}
+
+/// Rules a game is played with
+#[derive(Clone, Debug)]
+pub struct Rules {
+    /// Cells across the board
+    pub width: usize,
+    /// Visible rows
+    pub height: usize,
+    /// Hidden rows above the visible ones, where tetrominos spawn
+    pub buffer_height: usize,
+    pub rotation_system: RotationSystem,
+    pub generator: GeneratorKind,
+    pub scoring: ScoringKind,
+    pub gravity: Gravity,
+    /// Number of upcoming pieces kept in the queue
+    pub preview_count: usize,
+    /// Seconds a tetromino can rest on the ground before locking
+    pub lock_delay: f32,
+    /// Number of times moving or rotating can restart the lock delay, `None` for unlimited
+    pub max_lock_resets: Option<usize>,
+    pub spawn_delays: SpawnDelays,
+    /// Chance of each garbage row's hole moving from the column of the row below, from 0 to 1
+    pub garbage_messiness: f32,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            width: BOARD_WIDTH,
+            height: BOARD_HEIGHT,
+            buffer_height: BUFFER_HEIGHT,
+            rotation_system: RotationSystem::default(),
+            generator: GeneratorKind::default(),
+            scoring: ScoringKind::default(),
+            gravity: Gravity::default(),
+            preview_count: 5,
+            lock_delay: 0.5,
+            max_lock_resets: Some(15),
+            spawn_delays: SpawnDelays::default(),
+            garbage_messiness: 0.3,
+        }
+    }
+}
+
+/// Everything a player (or gravity) can do to the game
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateClockwise,
+    RotateAnticlockwise,
+    Rotate180,
+    Hold,
+    /// Moves the tetromino down one row, spawning one if there isn't one in play and the
+    /// game isn't waiting to spawn one
+    Gravity,
+    /// Spawns the next tetromino once the wait after the last locking is over. Holds and
+    /// rotations applied during the wait take effect here if the rules allow initial actions.
+    Spawn,
+    /// Locks the tetromino in play, e.g. once its lock delay has run out
+    Lock,
+    /// Pushes a row of garbage up from the bottom of the board
+    Garbage,
+}
+
+/// A single game of tetris. Cloning takes a snapshot that carries on exactly as the
+/// original would.
+#[derive(Clone)]
+pub struct Game {
+    seed: u64,
+    board: Board,
+    piece: Option<Piece>,
+    rules: Rules,
+    generator: Box<dyn PieceGenerator>,
+    queue: VecDeque<Tetrominos>,
+    held: Option<Tetrominos>,
+    can_hold: bool,
+    scoring: Box<dyn ScoringRules>,
+    garbage: GarbageGenerator,
+    score: usize,
+    lines: usize,
+    placed: usize,
+    garbage_cleared: usize,
+    over: bool,
+    /// Time the current tetromino has spent on the ground since its lock delay last reset,
+    /// `None` until it lands on a new lowest row. Kept while it is lifted back off the ground
+    /// so running out of resets can't be dodged by rotating up and landing again.
+    lock_timer: Option<f32>,
+    /// Whether the current tetromino is resting on the ground, running down its lock delay
+    grounded: bool,
+    /// Seconds left before the next tetromino spawns, `None` when not waiting for one
+    spawn_timer: Option<f32>,
+    /// Whether the next tetromino is swapped with the held one as it spawns (IHS)
+    initial_hold: bool,
+    /// Rotation state the next tetromino spawns in if it fits (IRS)
+    initial_rotation: usize,
+    lock_resets: usize,
+    /// Lowest row reached by the current tetromino, reaching a new one restores its resets
+    lowest_y: i32,
+    /// Clockwise turns and index of the kick used if the last successful move was a rotation
+    last_kick: Option<(usize, usize)>,
+}
+
+impl Game {
+    /// Creates a game. Every random decision is made from `seed`, so the same seed and the
+    /// same actions always give the same game.
+    pub fn new(rules: &Rules, seed: u64) -> Game {
+        let mut generator = rules.generator.create(seed);
+        let queue = (0..rules.preview_count).map(|_| generator.next()).collect();
+
+        Game {
+            seed,
+            board: Board::new(rules.width, rules.height + rules.buffer_height),
+            piece: None,
+            rules: rules.clone(),
+            generator,
+            queue,
+            held: None,
+            can_hold: true,
+            scoring: rules.scoring.create(),
+            garbage: GarbageGenerator::new(seed, rules.garbage_messiness),
+            score: 0,
+            lines: 0,
+            placed: 0,
+            garbage_cleared: 0,
+            over: false,
+            lock_timer: None,
+            grounded: false,
+            spawn_timer: None,
+            initial_hold: false,
+            initial_rotation: 0,
+            lock_resets: 0,
+            lowest_y: 0,
+            last_kick: None,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Every row of the board, including the hidden ones above `visible_height`
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Rows of the board the player normally sees
+    pub fn visible_height(&self) -> usize {
+        self.rules.height
+    }
+
+    /// The tetromino in play
+    pub fn piece(&self) -> Option<&Piece> {
+        self.piece.as_ref()
+    }
+
+    /// Where the tetromino in play would land if hard dropped
+    pub fn ghost(&self) -> Option<Piece> {
+        let mut ghost = self.piece?;
+        while ghost.shifted(0, -1).fits(&self.board) {
+            ghost = ghost.shifted(0, -1);
+        }
+        Some(ghost)
+    }
+
+    /// Upcoming tetrominos, next to spawn first
+    pub fn queue(&self) -> &VecDeque<Tetrominos> {
+        &self.queue
+    }
+
+    /// Tetromino in the hold slot
+    pub fn held(&self) -> Option<&Tetrominos> {
+        self.held.as_ref()
+    }
+
+    /// Whether the current tetromino can be swapped into the hold slot
+    pub fn can_hold(&self) -> bool {
+        self.can_hold
+    }
+
+    pub fn score(&self) -> usize {
+        self.score
+    }
+
+    /// Total lines cleared
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    /// Tetrominos locked so far
+    pub fn placed(&self) -> usize {
+        self.placed
+    }
+
+    /// Lines cleared that contained garbage
+    pub fn garbage_cleared(&self) -> usize {
+        self.garbage_cleared
+    }
+
+    /// Level used to multiply scores, going up every 10 lines
+    pub fn level(&self) -> usize {
+        self.lines / 10 + 1
+    }
+
+    /// Seconds between gravity moving the tetromino down a row
+    pub fn gravity_interval(&self) -> f32 {
+        self.rules.gravity.interval(self.lines)
+    }
+
+    /// Whether the player has topped out
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    /// Performs `action`, returning whether it had any effect
+    pub fn apply(&mut self, action: Action) -> bool {
+        if self.over {
+            return false;
+        }
+        if matches!(action, Action::Garbage) {
+            self.add_garbage(1);
+            return true;
+        }
+        if self.piece.is_none() {
+            return match action {
+                Action::Gravity => self.spawn_timer.is_none() && self.spawn(),
+                Action::Spawn => {
+                    self.spawn_timer = None;
+                    self.spawn()
+                }
+                Action::Hold if self.accepts_initial_actions() && self.can_hold => {
+                    self.initial_hold = true;
+                    true
+                }
+                Action::RotateClockwise if self.accepts_initial_actions() => {
+                    self.initial_rotation = 1;
+                    true
+                }
+                Action::RotateAnticlockwise if self.accepts_initial_actions() => {
+                    self.initial_rotation = 3;
+                    true
+                }
+                Action::Rotate180 if self.accepts_initial_actions() => {
+                    self.initial_rotation = 2;
+                    true
+                }
+                _ => false,
+            };
+        }
+
+        match action {
+            Action::MoveLeft => self.shift(-1),
+            Action::MoveRight => self.shift(1),
+            Action::SoftDrop => {
+                let moved = self.fall();
+                if moved {
+                    self.score += self.scoring.soft_drop(1);
+                }
+                moved
+            }
+            Action::HardDrop => {
+                let mut cells = 0;
+                while self.down() {
+                    cells += 1;
+                }
+                self.score += self.scoring.hard_drop(cells);
+                self.lock();
+                true
+            }
+            Action::RotateClockwise => self.rotate(1),
+            Action::RotateAnticlockwise => self.rotate(3),
+            Action::Rotate180 => self.rotate(2),
+            Action::Hold => self.hold(),
+            Action::Gravity => self.fall(),
+            Action::Lock => {
+                self.lock();
+                true
+            }
+            Action::Spawn | Action::Garbage => false,
+        }
+    }
+
+    /// Adds `count` rows of garbage to the bottom of the board, pushing the tetromino in play
+    /// up if it would overlap them. Tops out if blocks are pushed off the top or the
+    /// tetromino can't be pushed clear.
+    pub fn add_garbage(&mut self, count: usize) {
+        if self.over || count == 0 {
+            return;
+        }
+
+        let width = self.board.width();
+        let rows: Vec<_> = (0..count).map(|_| self.garbage.next_row(width)).collect();
+        if !self.board.insert_rows(&rows) {
+            self.over = true;
+        }
+
+        if let Some(piece) = self.piece {
+            if !piece.fits(&self.board) {
+                match (1..=count as i32)
+                    .map(|dy| piece.shifted(0, dy))
+                    .find(|piece| piece.fits(&self.board))
+                {
+                    Some(piece) => self.piece = Some(piece),
+                    None => self.over = true,
+                }
+            }
+        }
+    }
+
+    /// Empties the board after topping out and spawns the next tetromino, letting the game
+    /// carry on
+    pub fn clear_board(&mut self) {
+        self.board = Board::new(self.board.width(), self.board.height());
+        self.piece = None;
+        self.spawn_timer = None;
+        self.initial_hold = false;
+        self.initial_rotation = 0;
+        self.over = false;
+        self.can_hold = true;
+        self.spawn();
+    }
+
+    /// Advances the lock delay by `delta` seconds, returning true if the current tetromino
+    /// should lock
+    pub fn lock_due(&mut self, delta: f32) -> bool {
+        if self.piece.is_none() || self.over || !self.grounded {
+            return false;
+        }
+
+        match &mut self.lock_timer {
+            Some(timer) => {
+                *timer += delta;
+                *timer >= self.rules.lock_delay
+            }
+            None => false,
+        }
+    }
+
+    /// Advances the wait for the next tetromino by `delta` seconds, returning true if it
+    /// should spawn
+    pub fn spawn_due(&mut self, delta: f32) -> bool {
+        if self.piece.is_some() || self.over {
+            return false;
+        }
+
+        match &mut self.spawn_timer {
+            Some(timer) => {
+                *timer -= delta;
+                *timer <= 0.0
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the game is waiting out a delay before spawning the next tetromino
+    pub fn spawn_pending(&self) -> bool {
+        self.spawn_timer.is_some()
+    }
+
+    /// Whether rotations and holds held while waiting should apply to the next tetromino
+    pub fn initial_actions(&self) -> bool {
+        self.rules.spawn_delays.initial_actions
+    }
+
+    fn accepts_initial_actions(&self) -> bool {
+        self.initial_actions() && self.spawn_timer.is_some()
+    }
+
+    fn spawn(&mut self) -> bool {
+        if self.piece.is_some() {
+            panic!("Tried to spawn tetromino while one is in play!");
+        }
+
+        let mut shape = self.next_shape();
+        // Resolved before the block out check so a held tetromino that fits saves the game
+        if std::mem::take(&mut self.initial_hold) {
+            self.can_hold = false;
+            shape = match self.held.replace(shape) {
+                Some(held) => held,
+                None => self.next_shape(),
+            };
+        }
+        let rot = std::mem::take(&mut self.initial_rotation);
+        self.spawn_shape(shape, rot)
+    }
+
+    fn next_shape(&mut self) -> Tetrominos {
+        self.queue.push_back(self.generator.next());
+        self.queue.pop_front().unwrap()
+    }
+
+    /// Puts `shape` in play just above the visible rows, or at the top of the board without
+    /// enough hidden rows, in rotation state `rot` if it fits there. Like the guideline, a
+    /// tetromino spawning in the hidden rows drops a row straight away if it can, bringing it
+    /// into view. Tops out if it overlaps a block where it ends up (block out).
+    fn spawn_shape(&mut self, shape: Tetrominos, rot: usize) -> bool {
+        let place = |rot| {
+            let piece = Piece {
+                shape,
+                x: (self.board.width() / 2) as i32 - (4 / 2),
+                y: (self.visible_height() + 1).min(self.board.height() - 1) as i32,
+                rot,
+            };
+            let dropped = piece.shifted(0, -1);
+            if piece.y >= self.visible_height() as i32 && dropped.fits(&self.board) {
+                dropped
+            } else {
+                piece
+            }
+        };
+        let mut piece = place(rot);
+        if !piece.fits(&self.board) {
+            piece = place(0);
+        }
+
+        self.lock_timer = None;
+        self.grounded = false;
+        self.lock_resets = 0;
+        self.lowest_y = piece.y;
+        self.last_kick = None;
+
+        if !piece.fits(&self.board) {
+            self.over = true;
+            return false;
+        }
+
+        self.piece = Some(piece);
+        true
+    }
+
+    /// Locks the current tetromino in place, clears lines and spawns the next tetromino. Tops
+    /// out if the tetromino is entirely above the visible rows (lock out).
+    fn lock(&mut self) {
+        let piece = self.piece.take().unwrap();
+        for (x, y) in piece.blocks() {
+            self.board
+                .set(x as usize, y as usize, piece.shape.get_color());
+        }
+        let locked_out = piece
+            .blocks()
+            .all(|(_, y)| y >= self.visible_height() as i32);
+
+        let clear = self.board.clear_lines();
+        let info = LockInfo {
+            lines: clear.rows.len(),
+            spin: self.spin(&piece, &clear.before),
+            perfect_clear: self.board.is_empty(),
+        };
+        self.score += self.scoring.lock(&info, self.level());
+        self.lines += clear.rows.len();
+        self.garbage_cleared += clear
+            .rows
+            .iter()
+            .filter(|&&y| {
+                clear
+                    .before
+                    .iter()
+                    .any(|column| column[y] == Colors::Garbage)
+            })
+            .count();
+        self.placed += 1;
+
+        self.can_hold = true;
+        if locked_out {
+            self.over = true;
+            return;
+        }
+
+        let delays = &self.rules.spawn_delays;
+        let wait = delays.entry
+            + if info.lines > 0 {
+                delays.line_clear
+            } else {
+                0.0
+            };
+        if wait > 0.0 {
+            self.spawn_timer = Some(wait);
+        } else {
+            self.spawn();
+        }
+    }
+
+    /// Checks whether `piece` is a T-spin using the 3-corner rule. A T-spin with only one of
+    /// the corners the T points towards filled is a mini unless the last rotation was a quarter
+    /// turn that needed the final SRS kick.
+    fn spin(&self, piece: &Piece, board: &Cells) -> Spin {
+        let (Tetrominos::TShape, Some((turns, kick))) = (piece.shape, self.last_kick) else {
+            return Spin::None;
+        };
+
+        let (cx, cy) = (piece.x + 1, piece.y - 1);
+        let filled = |dx: i32, dy: i32| {
+            let (x, y) = (cx + dx, cy + dy);
+            x < 0
+                || y < 0
+                || x >= self.board.width() as i32
+                || (y < self.board.height() as i32 && !board[x as usize][y as usize].is_empty())
+        };
+
+        // Top left, top right, bottom right, bottom left
+        let corners = [filled(-1, 1), filled(1, 1), filled(1, -1), filled(-1, -1)];
+        if corners.iter().filter(|filled| **filled).count() < 3 {
+            return Spin::None;
+        }
+
+        // Corners either side of the direction the T points in
+        let front = [corners[piece.rot % 4], corners[(piece.rot + 1) % 4]];
+        if front.iter().all(|filled| *filled) || (turns != 2 && kick == 4) {
+            Spin::Full
+        } else {
+            Spin::Mini
+        }
+    }
+
+    /// Swaps the current tetromino with the held one
+    fn hold(&mut self) -> bool {
+        if !self.can_hold {
+            return false;
+        }
+
+        let current = self.piece.take().unwrap();
+        self.can_hold = false;
+
+        match self.held.replace(current.shape) {
+            Some(held) => self.spawn_shape(held, 0),
+            None => self.spawn(),
+        };
+        true
+    }
+
+    /// Moves the tetromino down one row, starting the lock delay if it is on the ground
+    fn fall(&mut self) -> bool {
+        let moved = self.down();
+        if !moved {
+            self.land();
+        }
+        moved
+    }
+
+    fn down(&mut self) -> bool {
+        let piece = self.piece.unwrap().shifted(0, -1);
+        if !piece.fits(&self.board) {
+            return false;
+        }
+
+        self.piece = Some(piece);
+        self.grounded = false;
+        self.last_kick = None;
+        if piece.y < self.lowest_y {
+            self.lowest_y = piece.y;
+            self.lock_timer = None;
+            self.lock_resets = 0;
+        }
+        if self.on_ground() {
+            self.land();
+        }
+        true
+    }
+
+    fn shift(&mut self, dx: i32) -> bool {
+        let piece = self.piece.unwrap().shifted(dx, 0);
+        if !piece.fits(&self.board) {
+            return false;
+        }
+
+        self.piece = Some(piece);
+        self.last_kick = None;
+        self.moved();
+        true
+    }
+
+    /// Rotates clockwise by `turns` quarter turns, trying each kick offset of the rotation
+    /// system in turn
+    fn rotate(&mut self, turns: usize) -> bool {
+        let current = self.piece.unwrap();
+        let rot = (current.rot + turns) % 4;
+        let kicks = self
+            .rules
+            .rotation_system
+            .kicks(&current.shape, current.rot, rot);
+
+        for (i, (dx, dy)) in kicks.iter().enumerate() {
+            let piece = Piece {
+                rot,
+                ..current.shifted(*dx, *dy)
+            };
+            if piece.fits(&self.board) {
+                self.piece = Some(piece);
+                self.last_kick = Some((turns, i));
+                self.moved();
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Starts the lock delay, carrying on from where it was if the tetromino has already
+    /// been on the ground at this height
+    fn land(&mut self) {
+        self.grounded = true;
+        if self.lock_timer.is_none() {
+            self.lock_timer = Some(0.0);
+        }
+    }
+
+    /// Resets the lock delay after a successful move or rotation on the ground while resets
+    /// remain, pausing it if the tetromino is no longer on the ground
+    fn moved(&mut self) {
+        if self.grounded
+            && self
+                .rules
+                .max_lock_resets
+                .is_none_or(|max| self.lock_resets < max)
+        {
+            self.lock_resets += 1;
+            self.lock_timer = Some(0.0);
+        }
+
+        if self.on_ground() {
+            self.land();
+        } else {
+            self.grounded = false;
+        }
+    }
+
+    fn on_ground(&self) -> bool {
+        !self.piece.unwrap().shifted(0, -1).fits(&self.board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A game with `shape` in play, before anything has been applied
+    fn game_with(shape: Tetrominos) -> Game {
+        let mut game = Game::new(&Rules::default(), 0);
+        game.spawn_shape(shape, 0);
+        game
+    }
+
+    /// Spin awarded for a T in rotation `rot` centred on (4, 4) with the given corners
+    /// (top left, top right, bottom right, bottom left) filled after the rotation `last_kick`
+    fn spin_with(rot: usize, corners: [bool; 4], last_kick: Option<(usize, usize)>) -> Spin {
+        let mut game = game_with(Tetrominos::TShape);
+        let offsets = [(-1, 1), (1, 1), (1, -1), (-1, -1)];
+        let mut board = Board::default();
+        for ((dx, dy), filled) in offsets.into_iter().zip(corners) {
+            if filled {
+                board.set((4 + dx) as usize, (4 + dy) as usize, Colors::Red);
+            }
+        }
+        let piece = Piece {
+            shape: Tetrominos::TShape,
+            x: 3,
+            y: 5,
+            rot,
+        };
+        game.last_kick = last_kick;
+
+        game.spin(&piece, board.cells())
+    }
+
+    #[test]
+    fn t_spin_needs_three_corners() {
+        assert_eq!(
+            spin_with(2, [true, false, true, true], Some((1, 0))),
+            Spin::Full
+        );
+        assert_eq!(
+            spin_with(2, [true, true, true, true], Some((1, 0))),
+            Spin::Full
+        );
+        assert_eq!(
+            spin_with(2, [false, false, true, true], Some((1, 0))),
+            Spin::None
+        );
+    }
+
+    #[test]
+    fn t_spin_with_one_front_corner_is_mini() {
+        assert_eq!(
+            spin_with(0, [true, false, true, true], Some((3, 0))),
+            Spin::Mini
+        );
+        assert_eq!(
+            spin_with(1, [true, false, true, true], Some((1, 2))),
+            Spin::Mini
+        );
+    }
+
+    #[test]
+    fn last_srs_kick_upgrades_mini_to_full() {
+        assert_eq!(
+            spin_with(0, [true, false, true, true], Some((1, 4))),
+            Spin::Full
+        );
+    }
+
+    #[test]
+    fn half_turn_kicks_do_not_upgrade_mini() {
+        assert_eq!(
+            spin_with(0, [true, false, true, true], Some((2, 4))),
+            Spin::Mini
+        );
+    }
+
+    #[test]
+    fn t_spin_needs_rotation_last() {
+        assert_eq!(spin_with(2, [true, true, true, true], None), Spin::None);
+    }
+
+    #[test]
+    fn t_spin_counts_walls_as_corners() {
+        let mut game = game_with(Tetrominos::TShape);
+        let mut board = Board::default();
+        board.set(1, 0, Colors::Red);
+        // Pointing right, standing on the floor against the left wall
+        let piece = Piece {
+            shape: Tetrominos::TShape,
+            x: -1,
+            y: 2,
+            rot: 1,
+        };
+        game.last_kick = Some((1, 0));
+
+        assert_eq!(game.spin(&piece, board.cells()), Spin::Mini);
+    }
+
+    #[test]
+    fn spawned_tetromino_drops_into_view() {
+        for shape in Tetrominos::ALL {
+            let game = game_with(shape);
+            let visible = game.visible_height() as i32;
+
+            assert!(game.piece().unwrap().blocks().any(|(_, y)| y < visible));
+        }
+    }
+
+    #[test]
+    fn spawned_tetromino_stays_hidden_without_room_to_drop() {
+        let mut game = Game::new(&Rules::default(), 0);
+        let visible = game.visible_height();
+        game.board.set(4, visible - 1, Colors::Garbage);
+        game.spawn_shape(Tetrominos::TShape, 0);
+
+        assert!(!game.is_over());
+        assert!(game
+            .piece()
+            .unwrap()
+            .blocks()
+            .all(|(_, y)| y >= visible as i32));
+    }
+
+    /// A game with the TGM delays waiting for `next` to spawn, with `held` in the hold slot
+    fn waiting_for(next: Tetrominos, held: Option<Tetrominos>) -> Game {
+        let rules = Rules {
+            spawn_delays: SpawnDelays::TGM,
+            ..Rules::default()
+        };
+        let mut game = Game::new(&rules, 0);
+        game.queue[0] = next;
+        game.held = held;
+        game.spawn_timer = Some(0.0);
+        game
+    }
+
+    #[test]
+    fn initial_hold_is_checked_for_block_out() {
+        let mut game = waiting_for(Tetrominos::TShape, Some(Tetrominos::Line));
+        // Blocks the T where it spawns but leaves room for the I a row lower
+        game.board.set(4, 21, Colors::Garbage);
+        game.board.set(4, 20, Colors::Garbage);
+
+        assert!(game.apply(Action::Hold));
+        assert!(game.apply(Action::Spawn));
+        assert!(!game.is_over());
+        assert_eq!(game.piece().unwrap().shape, Tetrominos::Line);
+        assert_eq!(game.held(), Some(&Tetrominos::TShape));
+        assert!(!game.can_hold());
+    }
+
+    #[test]
+    fn initial_rotation_spawns_rotated() {
+        let mut game = waiting_for(Tetrominos::TShape, None);
+
+        assert!(game.apply(Action::RotateAnticlockwise));
+        assert!(game.apply(Action::Spawn));
+        assert_eq!(game.piece().unwrap().rot, 3);
+    }
+
+    #[test]
+    fn initial_actions_need_the_rules_to_allow_them() {
+        let mut game = waiting_for(Tetrominos::TShape, None);
+        game.rules.spawn_delays = SpawnDelays::CLASSIC;
+
+        assert!(!game.apply(Action::Hold));
+        assert!(!game.apply(Action::RotateClockwise));
+        assert!(game.apply(Action::Spawn));
+        assert_eq!(game.piece().unwrap().rot, 0);
+        assert!(game.held().is_none());
+    }
+
+    #[test]
+    fn moving_on_the_ground_resets_lock_delay_until_resets_run_out() {
+        let mut game = game_with(Tetrominos::TShape);
+        while game.apply(Action::Gravity) {}
+
+        for i in 0..15 {
+            assert!(!game.lock_due(0.4));
+            let action = if i % 2 == 0 {
+                Action::MoveLeft
+            } else {
+                Action::MoveRight
+            };
+            assert!(game.apply(action));
+        }
+        assert!(!game.lock_due(0.4));
+        game.apply(Action::MoveLeft);
+        assert!(game.lock_due(0.1));
+    }
+
+    #[test]
+    fn rotating_off_the_ground_does_not_reset_lock_delay_forever() {
+        let mut game = game_with(Tetrominos::TShape);
+        while game.apply(Action::Gravity) {}
+
+        // Rotating back and forth lifts the T off the floor with every kick
+        let mut elapsed = 0.0;
+        while !game.lock_due(0.05) {
+            game.apply(Action::RotateClockwise);
+            game.apply(Action::RotateAnticlockwise);
+            game.apply(Action::Gravity);
+            elapsed += 0.05;
+            assert!(elapsed < 5.0, "tetromino never locked");
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use crate::board::Colors;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Mixed into the game's seed so garbage doesn't follow the same sequence as the tetrominos
+const GARBAGE_SEED: u64 = 0x6761_7262_6167_6521;
+
+/// Makes rows of garbage with one hole each
+#[derive(Clone)]
+pub struct GarbageGenerator {
+    rng: StdRng,
+    /// Chance of each row's hole moving from the column of the row before, from 0 to 1
+    messiness: f32,
+    hole: Option<usize>,
+}
+
+impl GarbageGenerator {
+    /// The same `seed` and `messiness` always produce the same rows
+    pub fn new(seed: u64, messiness: f32) -> GarbageGenerator {
+        GarbageGenerator {
+            rng: StdRng::seed_from_u64(seed ^ GARBAGE_SEED),
+            messiness,
+            hole: None,
+        }
+    }
+
+    /// Row `width` cells long
+    pub fn next_row(&mut self, width: usize) -> Vec<Colors> {
+        let hole = match self.hole {
+            Some(hole) if self.rng.gen::<f32>() >= self.messiness => hole % width,
+            // Any column other than the last hole's
+            Some(hole) => (hole + self.rng.gen_range(1..width)) % width,
+            None => self.rng.gen_range(0..width),
+        };
+        self.hole = Some(hole);
+
+        let mut row = vec![Colors::Garbage; width];
+        row[hole] = Colors::Empty;
+        row
+    }
+}
@@ -0,0 +1,243 @@
+use crate::tetromino::Tetrominos;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::VecDeque;
+
+/// Source of the sequence of tetrominos played in a game
+pub trait PieceGenerator: Send + Sync {
+    fn next(&mut self) -> Tetrominos;
+
+    /// Copy of the generator in its current state, continuing the same sequence
+    fn box_clone(&self) -> Box<dyn PieceGenerator>;
+}
+
+impl Clone for Box<dyn PieceGenerator> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/// Which `PieceGenerator` a game uses
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash)]
+pub enum GeneratorKind {
+    #[default]
+    SevenBag,
+    FourteenBag,
+    Random,
+    TgmHistory,
+}
+
+impl GeneratorKind {
+    pub const ALL: [GeneratorKind; 4] = [
+        GeneratorKind::SevenBag,
+        GeneratorKind::FourteenBag,
+        GeneratorKind::Random,
+        GeneratorKind::TgmHistory,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GeneratorKind::SevenBag => "7-bag",
+            GeneratorKind::FourteenBag => "14-bag",
+            GeneratorKind::Random => "Random",
+            GeneratorKind::TgmHistory => "TGM",
+        }
+    }
+
+    pub fn next(&self) -> GeneratorKind {
+        match self {
+            GeneratorKind::SevenBag => GeneratorKind::FourteenBag,
+            GeneratorKind::FourteenBag => GeneratorKind::Random,
+            GeneratorKind::Random => GeneratorKind::TgmHistory,
+            GeneratorKind::TgmHistory => GeneratorKind::SevenBag,
+        }
+    }
+
+    /// Creates the generator. The same `seed` always produces the same sequence.
+    pub fn create(&self, seed: u64) -> Box<dyn PieceGenerator> {
+        let rng = StdRng::seed_from_u64(seed);
+        match self {
+            GeneratorKind::SevenBag => Box::new(BagGenerator::new(rng, 1)),
+            GeneratorKind::FourteenBag => Box::new(BagGenerator::new(rng, 2)),
+            GeneratorKind::Random => Box::new(RandomGenerator { rng }),
+            GeneratorKind::TgmHistory => Box::new(HistoryGenerator::new(rng, 4, 6)),
+        }
+    }
+}
+
+/// Deals out shuffled bags containing `copies` of every tetromino
+#[derive(Clone)]
+pub struct BagGenerator {
+    rng: StdRng,
+    copies: usize,
+    bag: Vec<Tetrominos>,
+}
+
+impl BagGenerator {
+    pub fn new(rng: StdRng, copies: usize) -> BagGenerator {
+        BagGenerator {
+            rng,
+            copies,
+            bag: Vec::with_capacity(Tetrominos::ALL.len() * copies),
+        }
+    }
+}
+
+impl PieceGenerator for BagGenerator {
+    fn box_clone(&self) -> Box<dyn PieceGenerator> {
+        Box::new(self.clone())
+    }
+
+    fn next(&mut self) -> Tetrominos {
+        if self.bag.is_empty() {
+            for _ in 0..self.copies {
+                self.bag.extend_from_slice(&Tetrominos::ALL);
+            }
+            self.bag.shuffle(&mut self.rng);
+        }
+
+        self.bag.pop().unwrap()
+    }
+}
+
+/// Every tetromino is equally likely regardless of history
+#[derive(Clone)]
+pub struct RandomGenerator {
+    rng: StdRng,
+}
+
+impl PieceGenerator for RandomGenerator {
+    fn box_clone(&self) -> Box<dyn PieceGenerator> {
+        Box::new(self.clone())
+    }
+
+    fn next(&mut self) -> Tetrominos {
+        self.rng.gen()
+    }
+}
+
+/// TGM style randomizer - rerolls up to `rolls` times while the piece is one of the last
+/// `history` pieces dealt. The first piece is never an S, Z or O.
+#[derive(Clone)]
+pub struct HistoryGenerator {
+    rng: StdRng,
+    history: VecDeque<Tetrominos>,
+    rolls: usize,
+    first: bool,
+}
+
+impl HistoryGenerator {
+    pub fn new(rng: StdRng, history: usize, rolls: usize) -> HistoryGenerator {
+        let history = [Tetrominos::ZShape, Tetrominos::BackZShape]
+            .into_iter()
+            .cycle()
+            .take(history)
+            .collect();
+
+        HistoryGenerator {
+            rng,
+            history,
+            rolls,
+            first: true,
+        }
+    }
+}
+
+impl PieceGenerator for HistoryGenerator {
+    fn box_clone(&self) -> Box<dyn PieceGenerator> {
+        Box::new(self.clone())
+    }
+
+    fn next(&mut self) -> Tetrominos {
+        let piece = if self.first {
+            self.first = false;
+            *[
+                Tetrominos::LShape,
+                Tetrominos::BackLShape,
+                Tetrominos::Line,
+                Tetrominos::TShape,
+            ]
+            .choose(&mut self.rng)
+            .unwrap()
+        } else {
+            let mut piece = self.rng.gen();
+            for _ in 1..self.rolls {
+                if !self.history.contains(&piece) {
+                    break;
+                }
+                piece = self.rng.gen();
+            }
+            piece
+        };
+
+        self.history.pop_front();
+        self.history.push_back(piece);
+
+        piece
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Number of each tetromino in `pieces`, in the order of `Tetrominos::ALL`
+    fn counts(pieces: &[Tetrominos]) -> Vec<usize> {
+        Tetrominos::ALL
+            .iter()
+            .map(|shape| pieces.iter().filter(|piece| *piece == shape).count())
+            .collect()
+    }
+
+    #[test]
+    fn bags_deal_each_tetromino_once_per_copy() {
+        for (kind, copies) in [
+            (GeneratorKind::SevenBag, 1),
+            (GeneratorKind::FourteenBag, 2),
+        ] {
+            let mut generator = kind.create(42);
+            let size = Tetrominos::ALL.len() * copies;
+
+            for _ in 0..20 {
+                let bag: Vec<_> = (0..size).map(|_| generator.next()).collect();
+                assert_eq!(counts(&bag), vec![copies; Tetrominos::ALL.len()]);
+            }
+        }
+    }
+
+    #[test]
+    fn seven_bag_never_repeats_more_than_twice() {
+        let mut generator = GeneratorKind::SevenBag.create(7);
+        let pieces: Vec<_> = (0..700).map(|_| generator.next()).collect();
+
+        assert!(pieces
+            .windows(3)
+            .all(|window| window[0] != window[1] || window[1] != window[2]));
+    }
+
+    #[test]
+    fn same_seed_same_sequence() {
+        for kind in GeneratorKind::ALL {
+            let mut a = kind.create(1234);
+            let mut b = kind.create(1234);
+            let mut copy = a.clone();
+            for _ in 0..100 {
+                let piece = a.next();
+                assert_eq!(piece, b.next());
+                assert_eq!(piece, copy.next());
+            }
+        }
+    }
+
+    #[test]
+    fn tgm_history_never_starts_with_s_z_or_o() {
+        for seed in 0..200 {
+            let first = GeneratorKind::TgmHistory.create(seed).next();
+            assert!(!matches!(
+                first,
+                Tetrominos::ZShape | Tetrominos::BackZShape | Tetrominos::Square
+            ));
+        }
+    }
+}
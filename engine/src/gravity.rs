@@ -0,0 +1,142 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Frames gravity is counted in
+const FRAME: f32 = 1.0 / 60.0;
+/// Frames per row for levels 1 to 18, from the guideline curve
+/// `(0.8 - (level - 1) * 0.007) ^ (level - 1)` seconds per row. Values under a frame move the
+/// tetromino several rows a frame.
+const GUIDELINE_FRAMES: [f32; 18] = [
+    60.0, 47.6, 37.1, 28.4, 21.3, 15.7, 11.4, 8.1, 5.6, 3.9, 2.6, 1.7, 1.1, 0.7, 0.4, 0.26, 0.15,
+    0.09,
+];
+/// Frames per row from level 19, where the curve passes 20 rows a frame (20G)
+const TWENTY_G_FRAMES: f32 = 1.0 / 20.0;
+
+/// How quickly tetrominos fall
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum Gravity {
+    /// Original t_tetris curve - `1 / (lines / 10 + 2)` seconds per row
+    #[default]
+    Classic,
+    /// The same number of seconds per row for the whole game
+    Fixed(f32),
+    /// Guideline curve by level, going up every 10 lines and reaching 20G
+    Guideline,
+    /// Tetrominos only move down when dropped
+    Off,
+}
+
+impl Gravity {
+    /// Choices offered to the player
+    pub const PRESETS: [Gravity; 6] = [
+        Gravity::Classic,
+        Gravity::Guideline,
+        Gravity::Off,
+        Gravity::Fixed(1.0),
+        Gravity::Fixed(0.5),
+        Gravity::Fixed(0.1),
+    ];
+
+    pub fn name(&self) -> String {
+        match self {
+            Gravity::Classic => "Classic t_tetris".to_string(),
+            Gravity::Fixed(interval) => format!("{interval}s per row"),
+            Gravity::Guideline => "Guideline".to_string(),
+            Gravity::Off => "Off".to_string(),
+        }
+    }
+
+    pub fn next(&self) -> Gravity {
+        let index = Gravity::PRESETS
+            .iter()
+            .position(|gravity| gravity == self)
+            .map_or(0, |index| index + 1);
+        Gravity::PRESETS[index % Gravity::PRESETS.len()]
+    }
+
+    /// Seconds between the tetromino moving down a row after `lines` lines have been cleared,
+    /// infinite without gravity
+    pub fn interval(&self, lines: usize) -> f32 {
+        match self {
+            Gravity::Classic => 1.0 / ((lines as f32 / 10.0) + 2.0),
+            Gravity::Fixed(interval) => *interval,
+            Gravity::Guideline => {
+                let frames = GUIDELINE_FRAMES
+                    .get(lines / 10)
+                    .copied()
+                    .unwrap_or(TWENTY_G_FRAMES);
+                frames * FRAME
+            }
+            Gravity::Off => f32::INFINITY,
+        }
+    }
+}
+
+/// Written as `classic`, `fixed <seconds>`, `guideline` or `off`
+impl Display for Gravity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Gravity::Classic => write!(f, "classic"),
+            Gravity::Fixed(interval) => write!(f, "fixed {interval}"),
+            Gravity::Guideline => write!(f, "guideline"),
+            Gravity::Off => write!(f, "off"),
+        }
+    }
+}
+
+impl FromStr for Gravity {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(' ') {
+            None if s == "classic" => Ok(Gravity::Classic),
+            None if s == "guideline" => Ok(Gravity::Guideline),
+            None if s == "off" => Ok(Gravity::Off),
+            Some(("fixed", interval)) => interval.parse().map(Gravity::Fixed).map_err(|_| ()),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Seconds per row at `level` on the guideline curve
+    fn guideline_curve(level: usize) -> f32 {
+        (0.8 - (level - 1) as f32 * 0.007).powi(level as i32 - 1)
+    }
+
+    #[test]
+    fn guideline_intervals_follow_the_curve() {
+        for level in [1, 2, 18] {
+            let interval = Gravity::Guideline.interval((level - 1) * 10);
+            let expected = guideline_curve(level);
+            assert!(
+                (interval - expected).abs() / expected < 0.05,
+                "level {level}: {interval} not {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn guideline_reaches_twenty_g_at_level_19() {
+        // 20 rows a frame at 60 frames a second
+        let twenty_g = 1.0 / 1200.0;
+        for lines in [180, 189, 250, 1000] {
+            assert!((Gravity::Guideline.interval(lines) - twenty_g).abs() < 1e-9);
+        }
+        assert!(Gravity::Guideline.interval(179) > twenty_g);
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for gravity in Gravity::PRESETS.into_iter().chain([Gravity::Fixed(0.25)]) {
+            assert_eq!(gravity.to_string().parse(), Ok(gravity));
+        }
+        for text in ["", "fixed", "fixed fast", "sideways"] {
+            assert_eq!(text.parse::<Gravity>(), Err(()));
+        }
+    }
+}
@@ -0,0 +1,228 @@
+/// Direction of horizontal movement
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Shift {
+    Left,
+    Right,
+}
+
+/// Number of cells to move when repeating instantly, i.e. as far as possible
+pub const INSTANT: usize = usize::MAX;
+
+/// Delayed Auto Shift - a held direction moves once, then after `das` seconds repeats every
+/// `arr` seconds (instantly if `arr` is 0). The charge isn't reset when a new tetromino
+/// spawns so it carries over between pieces.
+pub struct AutoShift {
+    das: f32,
+    arr: f32,
+    direction: Option<Shift>,
+    held_for: f32,
+}
+
+impl AutoShift {
+    pub fn new(das: f32, arr: f32) -> AutoShift {
+        AutoShift {
+            das,
+            arr,
+            direction: None,
+            held_for: 0.0,
+        }
+    }
+
+    /// Advances by `delta` seconds given the state of the left and right inputs, returning
+    /// the direction and number of cells to move (`INSTANT` to move as far as possible)
+    pub fn update(
+        &mut self,
+        left_held: bool,
+        left_pressed: bool,
+        right_held: bool,
+        right_pressed: bool,
+        delta: f32,
+    ) -> Option<(Shift, usize)> {
+        let held = |shift| match shift {
+            Shift::Left => left_held,
+            Shift::Right => right_held,
+        };
+
+        // The most recently pressed direction takes priority
+        let pressed = if right_pressed {
+            Some(Shift::Right)
+        } else if left_pressed {
+            Some(Shift::Left)
+        } else {
+            None
+        };
+        if let Some(shift) = pressed {
+            self.direction = Some(shift);
+            self.held_for = 0.0;
+            return Some((shift, 1));
+        }
+
+        let shift = match self.direction {
+            Some(shift) if held(shift) => shift,
+            _ => {
+                // Fall back to the other direction if it's still held, charging from scratch
+                self.direction = [Shift::Left, Shift::Right]
+                    .into_iter()
+                    .find(|shift| held(*shift));
+                self.held_for = 0.0;
+                return None;
+            }
+        };
+
+        let before = self.held_for;
+        self.held_for += delta;
+
+        if self.held_for < self.das {
+            None
+        } else if self.arr <= 0.0 {
+            Some((shift, INSTANT))
+        } else {
+            let moves = self.repeats(self.held_for) - self.repeats(before);
+            (moves > 0).then_some((shift, moves))
+        }
+    }
+
+    /// Number of automatic moves made after a direction has been held for `held_for` seconds
+    fn repeats(&self, held_for: f32) -> usize {
+        if held_for < self.das {
+            0
+        } else {
+            ((held_for - self.das) / self.arr) as usize + 1
+        }
+    }
+}
+
+/// Soft drop - moves down once when pressed, then every gravity interval divided by the
+/// soft drop factor while held
+pub struct SoftDrop {
+    factor: f32,
+    timer: f32,
+}
+
+impl SoftDrop {
+    pub fn new(factor: f32) -> SoftDrop {
+        SoftDrop { factor, timer: 0.0 }
+    }
+
+    /// Advances by `delta` seconds, returning the number of cells to move down (`INSTANT` to
+    /// move as far as possible)
+    pub fn update(
+        &mut self,
+        held: bool,
+        pressed: bool,
+        delta: f32,
+        gravity_interval: f32,
+    ) -> usize {
+        if pressed {
+            self.timer = 0.0;
+            return 1;
+        }
+        if !held {
+            return 0;
+        }
+
+        let interval = gravity_interval / self.factor;
+        if interval <= 0.0 {
+            return INSTANT;
+        }
+
+        self.timer += delta;
+        let moves = (self.timer / interval) as usize;
+        self.timer -= moves as f32 * interval;
+        moves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAS: f32 = 0.25;
+    const ARR: f32 = 0.0625;
+
+    /// Holds right, already pressed, for `delta` seconds
+    fn hold_right(shift: &mut AutoShift, delta: f32) -> Option<(Shift, usize)> {
+        shift.update(false, false, true, false, delta)
+    }
+
+    #[test]
+    fn pressing_moves_once_then_waits_for_das() {
+        let mut shift = AutoShift::new(DAS, ARR);
+
+        assert_eq!(
+            shift.update(false, false, true, true, 0.0),
+            Some((Shift::Right, 1))
+        );
+        assert_eq!(hold_right(&mut shift, 0.125), None);
+        assert_eq!(hold_right(&mut shift, 0.0625), None);
+        assert_eq!(hold_right(&mut shift, 0.0625), Some((Shift::Right, 1)));
+        assert_eq!(hold_right(&mut shift, 0.03125), None);
+        assert_eq!(hold_right(&mut shift, 0.03125), Some((Shift::Right, 1)));
+    }
+
+    #[test]
+    fn long_frames_repeat_several_times() {
+        let mut shift = AutoShift::new(DAS, ARR);
+        shift.update(false, false, true, true, 0.0);
+
+        assert_eq!(
+            hold_right(&mut shift, DAS + 3.0 * ARR),
+            Some((Shift::Right, 4))
+        );
+        assert_eq!(hold_right(&mut shift, 2.0 * ARR), Some((Shift::Right, 2)));
+    }
+
+    #[test]
+    fn zero_arr_moves_instantly() {
+        let mut shift = AutoShift::new(DAS, 0.0);
+        shift.update(false, false, true, true, 0.0);
+
+        assert_eq!(hold_right(&mut shift, 0.125), None);
+        assert_eq!(hold_right(&mut shift, 0.125), Some((Shift::Right, INSTANT)));
+    }
+
+    #[test]
+    fn latest_direction_wins_then_falls_back_to_the_other() {
+        let mut shift = AutoShift::new(DAS, ARR);
+        shift.update(true, true, false, false, 0.0);
+        shift.update(true, false, false, false, 0.125);
+
+        assert_eq!(
+            shift.update(true, false, true, true, 0.0),
+            Some((Shift::Right, 1))
+        );
+        assert_eq!(
+            shift.update(true, false, true, false, DAS),
+            Some((Shift::Right, 1))
+        );
+
+        // Releasing right charges left from scratch
+        assert_eq!(shift.update(true, false, false, false, 0.125), None);
+        assert_eq!(shift.update(true, false, false, false, 0.125), None);
+        assert_eq!(
+            shift.update(true, false, false, false, 0.125),
+            Some((Shift::Left, 1))
+        );
+        assert_eq!(shift.update(false, false, false, false, DAS), None);
+    }
+
+    #[test]
+    fn soft_drop_divides_gravity_by_the_factor() {
+        let mut soft_drop = SoftDrop::new(4.0);
+
+        assert_eq!(soft_drop.update(true, true, 0.0, 1.0), 1);
+        assert_eq!(soft_drop.update(true, false, 0.5, 1.0), 2);
+        assert_eq!(soft_drop.update(true, false, 0.125, 1.0), 0);
+        assert_eq!(soft_drop.update(true, false, 0.125, 1.0), 1);
+        assert_eq!(soft_drop.update(false, false, 1.0, 1.0), 0);
+    }
+
+    #[test]
+    fn soft_drop_is_instant_without_an_interval() {
+        assert_eq!(SoftDrop::new(4.0).update(true, false, 0.0, 0.0), INSTANT);
+        assert_eq!(
+            SoftDrop::new(f32::INFINITY).update(true, false, 0.0, 1.0),
+            INSTANT
+        );
+    }
+}
@@ -0,0 +1,24 @@
+//! Rules of t_tetris without any rendering or input handling, so games can be run headless,
+//! driven by bots or shown by any frontend.
+
+pub mod board;
+pub mod game;
+pub mod garbage;
+pub mod generator;
+pub mod gravity;
+pub mod handling;
+pub mod replay;
+pub mod rotation;
+pub mod scoring;
+mod shapes;
+pub mod tetromino;
+
+pub use board::{Board, Colors, LineClear};
+pub use game::{Action, Game, Rules, SpawnDelays};
+pub use tetromino::{Piece, Tetrominos};
+
+/// Size of the visible board unless the rules choose another
+pub const BOARD_WIDTH: usize = 10;
+pub const BOARD_HEIGHT: usize = 20;
+/// Hidden rows above the visible board unless the rules choose another
+pub const BUFFER_HEIGHT: usize = 20;
@@ -0,0 +1,317 @@
+use crate::game::{Action, Rules};
+use crate::generator::GeneratorKind;
+use crate::rotation::RotationSystem;
+use crate::scoring::ScoringKind;
+use std::fmt::{Display, Formatter, Write};
+use std::ops::RangeInclusive;
+
+const HEADER: &str = "t_tetris replay 2";
+
+/// Board widths, visible heights and preview counts a replay can be played with
+const WIDTHS: RangeInclusive<usize> = 4..=40;
+const HEIGHTS: RangeInclusive<usize> = 10..=60;
+const PREVIEW_COUNTS: RangeInclusive<usize> = 1..=6;
+
+/// Something done to the game during a replay
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum ReplayAction {
+    Game(Action),
+    /// Takes back the last placed tetromino. A game can't undo itself, so front ends that
+    /// allow it restore their own snapshots of the game.
+    Undo,
+}
+
+impl From<Action> for ReplayAction {
+    fn from(action: Action) -> Self {
+        ReplayAction::Game(action)
+    }
+}
+
+/// An action and the time, in milliseconds of play, it was applied at
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct ReplayEvent {
+    pub time: u32,
+    pub action: ReplayAction,
+}
+
+/// Everything needed to play a game back - the seed, the rules and every action applied,
+/// including gravity and lock delay expiring
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub seed: u64,
+    pub rules: Rules,
+    /// Name of the mode the game was played in, left to the frontend to interpret
+    pub mode: String,
+    pub events: Vec<ReplayEvent>,
+}
+
+#[derive(Debug)]
+pub struct ReplayError(String);
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid replay: {}", self.0)
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl Replay {
+    pub fn new(seed: u64, rules: &Rules) -> Replay {
+        Replay {
+            seed,
+            rules: rules.clone(),
+            mode: String::new(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, time: u32, action: impl Into<ReplayAction>) {
+        self.events.push(ReplayEvent {
+            time,
+            action: action.into(),
+        });
+    }
+
+    /// Length of the replay in milliseconds
+    pub fn duration(&self) -> u32 {
+        self.events.last().map_or(0, |event| event.time)
+    }
+
+    /// Writes the replay as text. Events are stored one per line as the time since the
+    /// previous event followed by a single character for the action.
+    pub fn serialize(&self) -> String {
+        let rules = &self.rules;
+        let mut out = String::new();
+
+        let _ = writeln!(out, "{HEADER}");
+        let _ = writeln!(out, "seed {}", self.seed);
+        let _ = writeln!(out, "mode {}", self.mode);
+        let _ = writeln!(out, "width {}", rules.width);
+        let _ = writeln!(out, "height {}", rules.height);
+        let _ = writeln!(out, "buffer_height {}", rules.buffer_height);
+        let _ = writeln!(out, "rotation {}", rules.rotation_system.name());
+        let _ = writeln!(out, "generator {}", rules.generator.name());
+        let _ = writeln!(out, "scoring {}", rules.scoring.name());
+        let _ = writeln!(out, "gravity {}", rules.gravity);
+        let _ = writeln!(out, "preview {}", rules.preview_count);
+        let _ = writeln!(out, "lock_delay {}", rules.lock_delay);
+        match rules.max_lock_resets {
+            Some(max) => _ = writeln!(out, "lock_resets {max}"),
+            None => _ = writeln!(out, "lock_resets infinite"),
+        }
+        let delays = &rules.spawn_delays;
+        let _ = writeln!(out, "entry_delay {}", delays.entry);
+        let _ = writeln!(out, "line_clear_delay {}", delays.line_clear);
+        let _ = writeln!(out, "initial_actions {}", delays.initial_actions);
+        let _ = writeln!(out, "garbage_messiness {}", rules.garbage_messiness);
+        let _ = writeln!(out, "events");
+
+        let mut last = 0;
+        for event in &self.events {
+            let _ = writeln!(out, "{} {}", event.time - last, action_code(event.action));
+            last = event.time;
+        }
+
+        out
+    }
+
+    pub fn parse(text: &str) -> Result<Replay, ReplayError> {
+        let error = |message: &str| ReplayError(message.to_string());
+        let mut lines = text.lines();
+
+        let mut seed = None;
+        let mut mode = String::new();
+        let mut rules = Rules::default();
+
+        if lines.next() != Some(HEADER) {
+            return Err(error("unknown header"));
+        }
+
+        for line in lines.by_ref() {
+            if line == "events" {
+                break;
+            }
+
+            let (key, value) = line
+                .split_once(' ')
+                .ok_or_else(|| error("malformed setting"))?;
+            let invalid = || ReplayError(format!("invalid {key} '{value}'"));
+
+            match key {
+                "seed" => seed = Some(value.parse().map_err(|_| invalid())?),
+                "mode" => mode = value.to_string(),
+                "width" => rules.width = value.parse().map_err(|_| invalid())?,
+                "height" => rules.height = value.parse().map_err(|_| invalid())?,
+                "buffer_height" => rules.buffer_height = value.parse().map_err(|_| invalid())?,
+                "rotation" => {
+                    rules.rotation_system = find_by_name(&RotationSystem::ALL, value, |r| r.name())
+                        .ok_or_else(invalid)?
+                }
+                "generator" => {
+                    rules.generator = find_by_name(&GeneratorKind::ALL, value, |g| g.name())
+                        .ok_or_else(invalid)?
+                }
+                "scoring" => {
+                    rules.scoring =
+                        find_by_name(&ScoringKind::ALL, value, |s| s.name()).ok_or_else(invalid)?
+                }
+                "gravity" => rules.gravity = value.parse().map_err(|_| invalid())?,
+                "preview" => rules.preview_count = value.parse().map_err(|_| invalid())?,
+                "lock_delay" => rules.lock_delay = value.parse().map_err(|_| invalid())?,
+                "lock_resets" => {
+                    rules.max_lock_resets = match value {
+                        "infinite" => None,
+                        value => Some(value.parse().map_err(|_| invalid())?),
+                    }
+                }
+                "entry_delay" => rules.spawn_delays.entry = value.parse().map_err(|_| invalid())?,
+                "line_clear_delay" => {
+                    rules.spawn_delays.line_clear = value.parse().map_err(|_| invalid())?
+                }
+                "initial_actions" => {
+                    rules.spawn_delays.initial_actions = value.parse().map_err(|_| invalid())?
+                }
+                "garbage_messiness" => {
+                    rules.garbage_messiness = value.parse().map_err(|_| invalid())?
+                }
+                // Settings from newer versions are skipped
+                _ => {}
+            }
+        }
+
+        if !WIDTHS.contains(&rules.width) || !HEIGHTS.contains(&rules.height) {
+            return Err(error("board size out of range"));
+        }
+        if !PREVIEW_COUNTS.contains(&rules.preview_count) {
+            return Err(error("preview count out of range"));
+        }
+
+        let mut replay = Replay::new(seed.ok_or_else(|| error("missing seed"))?, &rules);
+        replay.mode = mode;
+        let mut time = 0u32;
+
+        for line in lines {
+            let (delta, code) = line
+                .split_once(' ')
+                .ok_or_else(|| error("malformed event"))?;
+            let delta: u32 = delta.parse().map_err(|_| error("invalid event time"))?;
+            let action = code
+                .chars()
+                .next()
+                .and_then(action_from_code)
+                .ok_or_else(|| error("unknown action"))?;
+
+            time = time
+                .checked_add(delta)
+                .ok_or_else(|| error("event time out of range"))?;
+            replay.record(time, action);
+        }
+
+        Ok(replay)
+    }
+}
+
+fn find_by_name<T: Copy>(all: &[T], name: &str, get_name: impl Fn(&T) -> &str) -> Option<T> {
+    all.iter().find(|item| get_name(item) == name).copied()
+}
+
+const ACTION_CODES: [(ReplayAction, char); 13] = [
+    (ReplayAction::Game(Action::MoveLeft), 'L'),
+    (ReplayAction::Game(Action::MoveRight), 'R'),
+    (ReplayAction::Game(Action::SoftDrop), 'D'),
+    (ReplayAction::Game(Action::HardDrop), 'H'),
+    (ReplayAction::Game(Action::RotateClockwise), 'C'),
+    (ReplayAction::Game(Action::RotateAnticlockwise), 'A'),
+    (ReplayAction::Game(Action::Rotate180), 'F'),
+    (ReplayAction::Game(Action::Hold), 'S'),
+    (ReplayAction::Game(Action::Gravity), 'G'),
+    (ReplayAction::Game(Action::Spawn), 'N'),
+    (ReplayAction::Game(Action::Lock), 'K'),
+    (ReplayAction::Game(Action::Garbage), 'X'),
+    (ReplayAction::Undo, 'U'),
+];
+
+fn action_code(action: ReplayAction) -> char {
+    ACTION_CODES
+        .iter()
+        .find(|(a, _)| *a == action)
+        .map(|(_, code)| *code)
+        .unwrap()
+}
+
+fn action_from_code(code: char) -> Option<ReplayAction> {
+    ACTION_CODES
+        .iter()
+        .find(|(_, c)| *c == code)
+        .map(|(action, _)| *action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::SpawnDelays;
+
+    #[test]
+    fn serialize_round_trips() {
+        let rules = Rules {
+            width: 8,
+            height: 16,
+            rotation_system: RotationSystem::Simple,
+            generator: GeneratorKind::TgmHistory,
+            preview_count: 3,
+            max_lock_resets: None,
+            spawn_delays: SpawnDelays::TGM,
+            ..Rules::default()
+        };
+        let mut replay = Replay::new(987654321, &rules);
+        for (i, (action, _)) in ACTION_CODES.iter().enumerate() {
+            replay.record(i as u32 * 150, *action);
+        }
+
+        let text = replay.serialize();
+        let parsed = Replay::parse(&text).unwrap();
+
+        assert_eq!(parsed.seed, replay.seed);
+        assert_eq!(parsed.events, replay.events);
+        assert_eq!(parsed.rules.width, 8);
+        assert_eq!(parsed.rules.generator, GeneratorKind::TgmHistory);
+        assert_eq!(parsed.rules.max_lock_resets, None);
+        assert_eq!(parsed.rules.spawn_delays, SpawnDelays::TGM);
+        assert_eq!(parsed.serialize(), text);
+    }
+
+    #[test]
+    fn unknown_settings_are_skipped() {
+        let replay = Replay::parse(&format!("{HEADER}\nseed 1\nfuture_setting 3\nevents\n"));
+        assert!(replay.is_ok());
+    }
+
+    #[test]
+    fn invalid_replays_are_rejected() {
+        assert!(Replay::parse("not a replay\nseed 1\nevents\n").is_err());
+        assert!(Replay::parse(&format!("{HEADER}\nevents\n")).is_err());
+        assert!(Replay::parse(&format!("{HEADER}\nseed 1\npreview x\nevents\n")).is_err());
+        assert!(Replay::parse(&format!("{HEADER}\nseed 1\nevents\n4294967295 G\n1 G\n")).is_err());
+    }
+
+    #[test]
+    fn out_of_range_settings_are_rejected() {
+        for setting in [
+            "width 3",
+            "width 41",
+            "height 9",
+            "height 61",
+            "preview 0",
+            "preview 7",
+        ] {
+            let replay = Replay::parse(&format!("{HEADER}\nseed 1\n{setting}\nevents\n"));
+            assert!(replay.is_err(), "{setting}");
+        }
+        for setting in ["width 4", "width 40", "height 10", "height 60", "preview 6"] {
+            let replay = Replay::parse(&format!("{HEADER}\nseed 1\n{setting}\nevents\n"));
+            assert!(replay.is_ok(), "{setting}");
+        }
+        assert!(Replay::parse(&format!("{HEADER}\nseed 1\nevents\n10 ?\n")).is_err());
+    }
+}
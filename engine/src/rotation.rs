@@ -0,0 +1,184 @@
+use crate::tetromino::Tetrominos;
+
+/// How a tetromino is moved when a rotation collides with the board
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash)]
+pub enum RotationSystem {
+    /// Original t_tetris behaviour - try one cell right then one cell left
+    Simple,
+    /// Guideline Super Rotation System with per-piece wall-kick tables
+    #[default]
+    Srs,
+}
+
+impl RotationSystem {
+    pub const ALL: [RotationSystem; 2] = [RotationSystem::Simple, RotationSystem::Srs];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RotationSystem::Simple => "Simple",
+            RotationSystem::Srs => "SRS",
+        }
+    }
+
+    pub fn next(&self) -> RotationSystem {
+        match self {
+            RotationSystem::Simple => RotationSystem::Srs,
+            RotationSystem::Srs => RotationSystem::Simple,
+        }
+    }
+
+    /// Offsets `(x, y)` (y pointing up) to try, in order, when rotating `shape` from rotation
+    /// state `from` to rotation state `to`. The first offset is always `(0, 0)`.
+    pub fn kicks(&self, shape: &Tetrominos, from: usize, to: usize) -> &'static [(i32, i32)] {
+        match self {
+            RotationSystem::Simple => &SIMPLE_KICKS,
+            RotationSystem::Srs => srs_kicks(shape, from, to),
+        }
+    }
+}
+
+const SIMPLE_KICKS: [(i32, i32); 3] = [(0, 0), (1, 0), (-1, 0)];
+
+const NO_KICKS: [(i32, i32); 1] = [(0, 0)];
+
+// Rotation states follow the guideline naming: 0 = spawn, 1 = R (clockwise from spawn),
+// 2 = two rotations, 3 = L (anticlockwise from spawn). Tables are indexed by the state
+// being rotated from.
+
+const JLSTZ_CLOCKWISE: [[(i32, i32); 5]; 4] = [
+    // 0 -> R
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+    // R -> 2
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    // 2 -> L
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    // L -> 0
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+];
+
+const JLSTZ_ANTICLOCKWISE: [[(i32, i32); 5]; 4] = [
+    // 0 -> L
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+    // R -> 0
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+    // 2 -> R
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+    // L -> 2
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+];
+
+const I_CLOCKWISE: [[(i32, i32); 5]; 4] = [
+    // 0 -> R
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+    // R -> 2
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+    // 2 -> L
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+    // L -> 0
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+];
+
+const I_ANTICLOCKWISE: [[(i32, i32); 5]; 4] = [
+    // 0 -> L
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+    // R -> 0
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+    // 2 -> R
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+    // L -> 2
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+];
+
+// 180 degree kicks (as used by TETR.IO's SRS+)
+const HALF_TURN: [[(i32, i32); 6]; 4] = [
+    // 0 -> 2
+    [(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)],
+    // R -> L
+    [(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)],
+    // 2 -> 0
+    [(0, 0), (0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)],
+    // L -> R
+    [(0, 0), (-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)],
+];
+
+const I_HALF_TURN: [[(i32, i32); 2]; 4] = [
+    // 0 -> 2
+    [(0, 0), (0, 1)],
+    // R -> L
+    [(0, 0), (1, 0)],
+    // 2 -> 0
+    [(0, 0), (0, -1)],
+    // L -> R
+    [(0, 0), (-1, 0)],
+];
+
+fn srs_kicks(shape: &Tetrominos, from: usize, to: usize) -> &'static [(i32, i32)] {
+    if matches!(shape, Tetrominos::Square) || from == to {
+        return &NO_KICKS;
+    }
+
+    let is_line = matches!(shape, Tetrominos::Line);
+
+    if to == (from + 1) % 4 {
+        if is_line {
+            &I_CLOCKWISE[from]
+        } else {
+            &JLSTZ_CLOCKWISE[from]
+        }
+    } else if from == (to + 1) % 4 {
+        if is_line {
+            &I_ANTICLOCKWISE[from]
+        } else {
+            &JLSTZ_ANTICLOCKWISE[from]
+        }
+    } else if is_line {
+        &I_HALF_TURN[from]
+    } else {
+        &HALF_TURN[from]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srs_matches_guideline_tables() {
+        let srs = RotationSystem::Srs;
+        assert_eq!(
+            srs.kicks(&Tetrominos::TShape, 0, 1),
+            &[(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)]
+        );
+        assert_eq!(
+            srs.kicks(&Tetrominos::Line, 0, 1),
+            &[(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)]
+        );
+        assert_eq!(srs.kicks(&Tetrominos::Square, 0, 1), &NO_KICKS);
+        assert_eq!(srs.kicks(&Tetrominos::Square, 0, 2), &NO_KICKS);
+    }
+
+    #[test]
+    fn srs_kicks_back_are_reversed() {
+        for shape in Tetrominos::ALL {
+            for from in 0..4 {
+                for to in [(from + 1) % 4, (from + 3) % 4] {
+                    let there = RotationSystem::Srs.kicks(&shape, from, to);
+                    let back = RotationSystem::Srs.kicks(&shape, to, from);
+                    let reversed: Vec<_> = there.iter().map(|(x, y)| (-x, -y)).collect();
+                    assert_eq!(back, reversed, "{shape:?} {from} -> {to}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_kick_table_tries_in_place_first() {
+        for system in RotationSystem::ALL {
+            for shape in Tetrominos::ALL {
+                for (from, to) in (0..4).flat_map(|from| (0..4).map(move |to| (from, to))) {
+                    assert_eq!(system.kicks(&shape, from, to)[0], (0, 0));
+                }
+            }
+        }
+    }
+}
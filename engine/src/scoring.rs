@@ -0,0 +1,282 @@
+/// Whether a lock counts as a T-spin
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug)]
+pub enum Spin {
+    #[default]
+    None,
+    Mini,
+    Full,
+}
+
+/// What happened when a tetromino locked
+#[derive(Clone, Copy, Default, Debug)]
+pub struct LockInfo {
+    pub lines: usize,
+    pub spin: Spin,
+    pub perfect_clear: bool,
+}
+
+/// Awards points for drops and locks. Implementations may keep state between locks, e.g.
+/// for combos.
+pub trait ScoringRules: Send + Sync {
+    /// Points for soft dropping `cells` cells
+    fn soft_drop(&mut self, cells: usize) -> usize;
+
+    /// Points for hard dropping `cells` cells
+    fn hard_drop(&mut self, cells: usize) -> usize;
+
+    /// Points for a tetromino locking at `level` (starting from 1)
+    fn lock(&mut self, info: &LockInfo, level: usize) -> usize;
+
+    /// Copy of the rules in their current state, e.g. keeping the combo going
+    fn box_clone(&self) -> Box<dyn ScoringRules>;
+}
+
+impl Clone for Box<dyn ScoringRules> {
+    fn clone(&self) -> Self {
+        self.box_clone()
+    }
+}
+
+/// Which `ScoringRules` a game uses
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash)]
+pub enum ScoringKind {
+    Classic,
+    #[default]
+    Guideline,
+}
+
+impl ScoringKind {
+    pub const ALL: [ScoringKind; 2] = [ScoringKind::Classic, ScoringKind::Guideline];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScoringKind::Classic => "Classic t_tetris",
+            ScoringKind::Guideline => "Guideline",
+        }
+    }
+
+    pub fn next(&self) -> ScoringKind {
+        match self {
+            ScoringKind::Classic => ScoringKind::Guideline,
+            ScoringKind::Guideline => ScoringKind::Classic,
+        }
+    }
+
+    pub fn create(&self) -> Box<dyn ScoringRules> {
+        match self {
+            ScoringKind::Classic => Box::new(ClassicScoring),
+            ScoringKind::Guideline => Box::new(GuidelineScoring::default()),
+        }
+    }
+}
+
+/// Original t_tetris scoring - 100 points doubled for every extra line cleared at once
+#[derive(Clone)]
+pub struct ClassicScoring;
+
+impl ScoringRules for ClassicScoring {
+    fn box_clone(&self) -> Box<dyn ScoringRules> {
+        Box::new(self.clone())
+    }
+
+    fn soft_drop(&mut self, _cells: usize) -> usize {
+        0
+    }
+
+    fn hard_drop(&mut self, _cells: usize) -> usize {
+        0
+    }
+
+    fn lock(&mut self, info: &LockInfo, _level: usize) -> usize {
+        if info.lines == 0 {
+            0
+        } else {
+            100 * 2usize.pow(info.lines as u32 - 1)
+        }
+    }
+}
+
+/// Guideline scoring with T-spins, combos, back-to-back and perfect clear bonuses
+#[derive(Clone, Default)]
+pub struct GuidelineScoring {
+    /// Number of consecutive line clearing locks minus one, `None` when not in a combo
+    combo: Option<usize>,
+    /// Whether the last line clear was a tetris or T-spin
+    back_to_back: bool,
+}
+
+impl ScoringRules for GuidelineScoring {
+    fn box_clone(&self) -> Box<dyn ScoringRules> {
+        Box::new(self.clone())
+    }
+
+    fn soft_drop(&mut self, cells: usize) -> usize {
+        cells
+    }
+
+    fn hard_drop(&mut self, cells: usize) -> usize {
+        cells * 2
+    }
+
+    fn lock(&mut self, info: &LockInfo, level: usize) -> usize {
+        let base = match (info.spin, info.lines) {
+            (Spin::None, 0) => 0,
+            (Spin::None, 1) => 100,
+            (Spin::None, 2) => 300,
+            (Spin::None, 3) => 500,
+            (Spin::None, _) => 800,
+            (Spin::Mini, 0) => 100,
+            (Spin::Mini, 1) => 200,
+            (Spin::Mini, _) => 400,
+            (Spin::Full, 0) => 400,
+            (Spin::Full, 1) => 800,
+            (Spin::Full, 2) => 1200,
+            (Spin::Full, _) => 1600,
+        };
+
+        if info.lines == 0 {
+            self.combo = None;
+            return base * level;
+        }
+
+        let difficult = info.lines >= 4 || info.spin != Spin::None;
+        let back_to_back = difficult && self.back_to_back;
+        self.back_to_back = difficult;
+
+        let combo = self.combo.map_or(0, |combo| combo + 1);
+        self.combo = Some(combo);
+
+        let mut score = if back_to_back { base * 3 / 2 } else { base };
+        score += 50 * combo;
+
+        if info.perfect_clear {
+            score += match info.lines {
+                1 => 800,
+                2 => 1200,
+                3 => 1800,
+                _ if back_to_back => 3200,
+                _ => 2000,
+            };
+        }
+
+        score * level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clear(lines: usize, spin: Spin) -> LockInfo {
+        LockInfo {
+            lines,
+            spin,
+            perfect_clear: false,
+        }
+    }
+
+    fn perfect_clear(lines: usize) -> LockInfo {
+        LockInfo {
+            perfect_clear: true,
+            ..clear(lines, Spin::None)
+        }
+    }
+
+    /// Points for each of `locks` in turn at `level`, starting from fresh guideline scoring
+    fn scores(locks: &[LockInfo], level: usize) -> Vec<usize> {
+        let mut scoring = GuidelineScoring::default();
+        locks.iter().map(|info| scoring.lock(info, level)).collect()
+    }
+
+    #[test]
+    fn line_clears_score_from_the_table() {
+        for (lines, points) in [(0, 0), (1, 100), (2, 300), (3, 500), (4, 800)] {
+            assert_eq!(scores(&[clear(lines, Spin::None)], 1), vec![points]);
+        }
+    }
+
+    #[test]
+    fn t_spins_score_from_the_table() {
+        for (lines, points) in [(0, 400), (1, 800), (2, 1200), (3, 1600)] {
+            assert_eq!(scores(&[clear(lines, Spin::Full)], 1), vec![points]);
+        }
+        for (lines, points) in [(0, 100), (1, 200), (2, 400)] {
+            assert_eq!(scores(&[clear(lines, Spin::Mini)], 1), vec![points]);
+        }
+    }
+
+    #[test]
+    fn combo_adds_fifty_per_clear_until_a_lock_clears_nothing() {
+        let single = clear(1, Spin::None);
+        let none = clear(0, Spin::None);
+
+        assert_eq!(
+            scores(&[single, single, single, single, none, single], 1),
+            vec![100, 150, 200, 250, 0, 100]
+        );
+    }
+
+    #[test]
+    fn back_to_back_multiplies_by_one_and_a_half() {
+        let tetris = clear(4, Spin::None);
+        let none = clear(0, Spin::None);
+
+        assert_eq!(
+            scores(
+                &[
+                    tetris,
+                    none,
+                    tetris,
+                    none,
+                    clear(1, Spin::None),
+                    none,
+                    tetris
+                ],
+                1
+            ),
+            vec![800, 0, 1200, 0, 100, 0, 800]
+        );
+        assert_eq!(
+            scores(&[clear(1, Spin::Full), none, clear(2, Spin::Mini)], 1),
+            vec![800, 0, 600]
+        );
+    }
+
+    #[test]
+    fn zero_line_t_spin_keeps_back_to_back() {
+        assert_eq!(
+            scores(
+                &[
+                    clear(1, Spin::Full),
+                    clear(0, Spin::Full),
+                    clear(4, Spin::None)
+                ],
+                1
+            ),
+            vec![800, 400, 1200]
+        );
+    }
+
+    #[test]
+    fn perfect_clears_add_a_bonus() {
+        for (lines, points) in [(1, 900), (2, 1500), (3, 2300), (4, 2800)] {
+            assert_eq!(scores(&[perfect_clear(lines)], 1), vec![points]);
+        }
+        assert_eq!(
+            scores(
+                &[clear(4, Spin::None), clear(0, Spin::None), perfect_clear(4)],
+                1
+            ),
+            vec![800, 0, 4400]
+        );
+    }
+
+    #[test]
+    fn points_are_multiplied_by_level() {
+        let single = clear(1, Spin::None);
+
+        assert_eq!(scores(&[clear(4, Spin::None)], 3), vec![2400]);
+        assert_eq!(scores(&[clear(0, Spin::Full)], 4), vec![1600]);
+        assert_eq!(scores(&[single, single], 2), vec![200, 300]);
+    }
+}
@@ -1,3 +1,7 @@
+// Shapes are indexed by [rotation][row][column] with row 0 at the top. Rotation 0 is the
+// guideline spawn orientation and each following rotation is a clockwise turn, laid out
+// in the same 4x4 / 3x3 boxes as the Super Rotation System so its kick tables apply as-is.
+
 pub const L_SHAPE: [[[bool; 4]; 4]; 4] = [
     [
         [false, false, true, false],
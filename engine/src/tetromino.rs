@@ -0,0 +1,89 @@
+use crate::board::{Board, Colors};
+use crate::shapes::{BACK_L_SHAPE, BACK_Z_SHAPE, LINE, L_SHAPE, SQUARE, T_SHAPE, Z_SHAPE};
+use rand_derive2::RandGen;
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, RandGen)]
+pub enum Tetrominos {
+    LShape,
+    BackLShape,
+    Line,
+    Square,
+    ZShape,
+    BackZShape,
+    TShape,
+}
+
+impl Tetrominos {
+    pub const ALL: [Tetrominos; 7] = [
+        Tetrominos::LShape,
+        Tetrominos::BackLShape,
+        Tetrominos::Line,
+        Tetrominos::Square,
+        Tetrominos::ZShape,
+        Tetrominos::BackZShape,
+        Tetrominos::TShape,
+    ];
+
+    pub fn get_shape(&self) -> &'static [[[bool; 4]; 4]; 4] {
+        match &self {
+            Tetrominos::LShape => &L_SHAPE,
+            Tetrominos::BackLShape => &BACK_L_SHAPE,
+            Tetrominos::Line => &LINE,
+            Tetrominos::Square => &SQUARE,
+            Tetrominos::ZShape => &Z_SHAPE,
+            Tetrominos::BackZShape => &BACK_Z_SHAPE,
+            Tetrominos::TShape => &T_SHAPE,
+        }
+    }
+
+    pub fn get_color(&self) -> Colors {
+        match &self {
+            Tetrominos::LShape => Colors::Orange,
+            Tetrominos::BackLShape => Colors::Blue,
+            Tetrominos::Line => Colors::LightBlue,
+            Tetrominos::Square => Colors::Yellow,
+            Tetrominos::ZShape => Colors::Red,
+            Tetrominos::BackZShape => Colors::Lime,
+            Tetrominos::TShape => Colors::Purple,
+        }
+    }
+}
+
+/// A tetromino placed on the board. `(x, y)` is the top left of its 4x4 shape with y pointing
+/// up, so the shape's rows extend downwards from `y`.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Piece {
+    pub shape: Tetrominos,
+    pub x: i32,
+    pub y: i32,
+    pub rot: usize,
+}
+
+impl Piece {
+    /// Board positions of the piece's blocks
+    pub fn blocks(&self) -> impl Iterator<Item = (i32, i32)> {
+        let shape = &self.shape.get_shape()[self.rot];
+        let (x, y) = (self.x, self.y);
+
+        shape.iter().enumerate().flat_map(move |(dy, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, filled)| **filled)
+                .map(move |(dx, _)| (x + dx as i32, y - dy as i32))
+        })
+    }
+
+    /// The piece moved by `(dx, dy)`
+    pub fn shifted(&self, dx: i32, dy: i32) -> Piece {
+        Piece {
+            x: self.x + dx,
+            y: self.y + dy,
+            ..*self
+        }
+    }
+
+    /// Whether every block is inside the board and on an empty cell
+    pub fn fits(&self, board: &Board) -> bool {
+        self.blocks().all(|(x, y)| board.is_free(x, y))
+    }
+}
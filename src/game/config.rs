@@ -0,0 +1,153 @@
+use crate::util::format_duration;
+use bevy::prelude::*;
+use t_tetris_engine::gravity::Gravity;
+use t_tetris_engine::Rules;
+
+pub const MIN_PREVIEW_COUNT: usize = 1;
+pub const MAX_PREVIEW_COUNT: usize = 6;
+/// Board widths offered, from 4 to 40 cells
+pub const BOARD_WIDTHS: [usize; 8] = [4, 6, 8, 10, 12, 16, 20, 40];
+/// Visible board heights offered, from 10 to 60 cells
+pub const BOARD_HEIGHTS: [usize; 7] = [10, 16, 20, 24, 30, 40, 60];
+
+/// Lines to clear in Sprint mode
+pub const SPRINT_GOALS: [usize; 3] = [20, 40, 100];
+/// Seconds per row in Sprint mode, level 1 guideline gravity
+const SPRINT_GRAVITY: f32 = 1.0;
+/// Seconds to score in during Ultra mode
+pub const ULTRA_TIMES: [u32; 3] = [120, 180, 300];
+/// Lines cleared to finish Marathon mode, reaching level 15
+pub const MARATHON_LINES: usize = 150;
+/// Garbage lines to dig through in Dig mode
+pub const DIG_GOALS: [usize; 3] = [10, 18, 100];
+/// Most garbage rows on the board at once in Dig mode, refilled as they are cleared
+pub const DIG_ROWS: usize = 10;
+
+/// Goal of a game, each keeping its own high scores
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash)]
+pub enum GameMode {
+    /// Play until topping out
+    #[default]
+    Endless,
+    /// Clear the given number of lines as quickly as possible
+    Sprint(usize),
+    /// Score as much as possible in the given number of seconds
+    Ultra(u32),
+    /// Guideline levels and gravity, ending after the given number of lines or never
+    Marathon(Option<usize>),
+    /// Practice without topping out, with undo
+    Zen,
+    /// Clear the given number of garbage lines as quickly as possible
+    Dig(usize),
+    /// Survive garbage rising from the bottom faster and faster
+    Survival,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 14] = [
+        GameMode::Endless,
+        GameMode::Sprint(SPRINT_GOALS[0]),
+        GameMode::Sprint(SPRINT_GOALS[1]),
+        GameMode::Sprint(SPRINT_GOALS[2]),
+        GameMode::Ultra(ULTRA_TIMES[0]),
+        GameMode::Ultra(ULTRA_TIMES[1]),
+        GameMode::Ultra(ULTRA_TIMES[2]),
+        GameMode::Marathon(Some(MARATHON_LINES)),
+        GameMode::Marathon(None),
+        GameMode::Zen,
+        GameMode::Dig(DIG_GOALS[0]),
+        GameMode::Dig(DIG_GOALS[1]),
+        GameMode::Dig(DIG_GOALS[2]),
+        GameMode::Survival,
+    ];
+
+    pub fn name(&self) -> String {
+        match self {
+            GameMode::Endless => "Endless".to_string(),
+            GameMode::Sprint(lines) => format!("Sprint {lines}"),
+            GameMode::Ultra(seconds) => format!("Ultra {}", format_duration(*seconds as f32)),
+            GameMode::Marathon(Some(lines)) => format!("Marathon {lines}"),
+            GameMode::Marathon(None) => "Marathon Endless".to_string(),
+            GameMode::Zen => "Zen".to_string(),
+            GameMode::Dig(lines) => format!("Dig {lines}"),
+            GameMode::Survival => "Survival".to_string(),
+        }
+    }
+
+    pub fn next(&self) -> GameMode {
+        let index = GameMode::ALL
+            .iter()
+            .position(|mode| mode == self)
+            .unwrap_or(0);
+        GameMode::ALL[(index + 1) % GameMode::ALL.len()]
+    }
+
+    /// Whether games are ranked by score, rather than by time
+    pub fn ranks_by_score(&self) -> bool {
+        match self {
+            GameMode::Endless | GameMode::Ultra(_) | GameMode::Marathon(_) | GameMode::Survival => {
+                true
+            }
+            GameMode::Sprint(_) | GameMode::Zen | GameMode::Dig(_) => false,
+        }
+    }
+
+    /// Whether topping out clears the board instead of ending the game, and placed
+    /// tetrominos can be taken back
+    pub fn is_practice(&self) -> bool {
+        matches!(self, GameMode::Zen)
+    }
+
+    /// Lines to clear to finish the game, if it has a goal
+    pub fn line_goal(&self) -> Option<usize> {
+        match self {
+            GameMode::Sprint(lines) => Some(*lines),
+            GameMode::Marathon(lines) => *lines,
+            _ => None,
+        }
+    }
+
+    /// Garbage lines to clear to finish the game, if it has garbage to dig through
+    pub fn garbage_goal(&self) -> Option<usize> {
+        match self {
+            GameMode::Dig(lines) => Some(*lines),
+            _ => None,
+        }
+    }
+
+    /// Seconds of play before the game ends, if it ends on time
+    pub fn time_limit(&self) -> Option<f32> {
+        match self {
+            GameMode::Ultra(seconds) => Some(*seconds as f32),
+            _ => None,
+        }
+    }
+
+    /// `rules` with the changes this mode needs
+    pub fn rules(&self, rules: &Rules) -> Rules {
+        match self {
+            GameMode::Endless
+            | GameMode::Ultra(_)
+            | GameMode::Zen
+            | GameMode::Dig(_)
+            | GameMode::Survival => rules.clone(),
+            GameMode::Sprint(_) => Rules {
+                gravity: Gravity::Fixed(SPRINT_GRAVITY),
+                ..rules.clone()
+            },
+            GameMode::Marathon(_) => Rules {
+                gravity: Gravity::Guideline,
+                ..rules.clone()
+            },
+        }
+    }
+}
+
+/// Mode and rules chosen in the menu for the next game
+#[derive(Resource, Clone, Default, Debug)]
+pub struct GameConfig {
+    pub mode: GameMode,
+    pub rules: Rules,
+    /// Seed used for every game of the session, `None` to pick a random seed per game
+    pub seed: Option<u64>,
+}
@@ -0,0 +1,360 @@
+use crate::game::config::GameMode;
+use crate::game::tetris_logic::TetrisLogic;
+use crate::game::OnGameScreen;
+use crate::loading::GlobalFont;
+use crate::util::data_dir;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Number of scores kept for each mode
+pub const MAX_HIGH_SCORES: usize = 10;
+pub const MAX_NAME_LENGTH: usize = 12;
+
+#[derive(Clone, Debug)]
+pub struct HighScore {
+    pub name: String,
+    pub score: usize,
+    pub lines: usize,
+    pub level: usize,
+    /// Seconds the game lasted
+    pub duration: f32,
+    /// Seconds since the unix epoch the game ended at
+    pub date: u64,
+}
+
+impl HighScore {
+    /// Tab separated fields, names can't contain tabs as they are typed in one line
+    fn serialize(&self, mode: GameMode) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            mode.name(),
+            self.score,
+            self.lines,
+            self.level,
+            self.duration,
+            self.date,
+            self.name
+        )
+    }
+
+    fn parse(line: &str) -> Option<(GameMode, HighScore)> {
+        let mut fields = line.splitn(7, '\t');
+        let mode = fields.next()?;
+        let mode = GameMode::ALL.into_iter().find(|m| m.name() == mode)?;
+
+        Some((
+            mode,
+            HighScore {
+                score: fields.next()?.parse().ok()?,
+                lines: fields.next()?.parse().ok()?,
+                level: fields.next()?.parse().ok()?,
+                duration: fields.next()?.parse().ok()?,
+                date: fields.next()?.parse().ok()?,
+                name: fields.next()?.to_string(),
+            },
+        ))
+    }
+}
+
+/// Best scores for each mode, highest first
+#[derive(Resource, Default)]
+pub struct HighScores {
+    scores: HashMap<GameMode, Vec<HighScore>>,
+}
+
+impl HighScores {
+    fn path() -> Option<PathBuf> {
+        data_dir().map(|dir| dir.join("high_scores.txt"))
+    }
+
+    /// Loads the saved high scores, starting a new table if there aren't any
+    pub fn load() -> HighScores {
+        let mut high_scores = HighScores::default();
+        let Some(text) = Self::path().and_then(|path| fs::read_to_string(path).ok()) else {
+            return high_scores;
+        };
+
+        for line in text.lines() {
+            match HighScore::parse(line) {
+                Some((mode, score)) => high_scores.insert(mode, score),
+                None => warn!("Skipping invalid high score '{line}'"),
+            }
+        }
+
+        high_scores
+    }
+
+    pub fn save(&self) {
+        let Some(path) = Self::path() else {
+            warn!("No data directory to save high scores in");
+            return;
+        };
+
+        let text: String = self
+            .scores
+            .iter()
+            .flat_map(|(mode, scores)| scores.iter().map(|score| score.serialize(*mode) + "\n"))
+            .collect();
+
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, text));
+        if let Err(e) = result {
+            warn!("Failed to save high scores to {}: {e}", path.display());
+        }
+    }
+
+    pub fn get(&self, mode: GameMode) -> &[HighScore] {
+        self.scores
+            .get(&mode)
+            .map_or(&[], |scores| scores.as_slice())
+    }
+
+    /// Whether `score` would make it onto the table for `mode`
+    pub fn qualifies(&self, mode: GameMode, score: usize) -> bool {
+        let scores = self.get(mode);
+        score > 0
+            && (scores.len() < MAX_HIGH_SCORES || scores.last().is_some_and(|s| score > s.score))
+    }
+
+    pub fn insert(&mut self, mode: GameMode, score: HighScore) {
+        let scores = self.scores.entry(mode).or_default();
+        let index = scores.partition_point(|s| s.score >= score.score);
+        scores.insert(index, score);
+        scores.truncate(MAX_HIGH_SCORES);
+    }
+}
+
+/// Name being typed for a new high score
+#[derive(Resource)]
+pub struct NameEntry {
+    name: String,
+}
+
+#[derive(Component)]
+pub struct NameEntryScreen;
+
+#[derive(Component)]
+pub struct NameEntryLabel;
+
+/// Asks for a name if the game that just ended made the high score table
+pub fn name_entry_setup(
+    mut commands: Commands,
+    font: Res<GlobalFont>,
+    high_scores: Res<HighScores>,
+    logic: Res<TetrisLogic>,
+) {
+    let mode = logic.mode();
+    if !mode.ranks_by_score() || !high_scores.qualifies(mode, logic.game().score()) {
+        return;
+    }
+
+    commands.insert_resource(NameEntry {
+        name: String::new(),
+    });
+
+    let text_font = |font_size| TextFont {
+        font: font.get(),
+        font_size,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            NameEntryScreen,
+            OnGameScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(30.0)),
+                        row_gap: Val::Px(15.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text("New high score!".to_string()),
+                        text_font(40.0),
+                        TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                    ));
+                    parent.spawn((
+                        Text("Name: _".to_string()),
+                        text_font(30.0),
+                        TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                        NameEntryLabel,
+                    ));
+                    parent.spawn((
+                        Text("[Save : Return / Start]".to_string()),
+                        text_font(20.0),
+                        TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                    ));
+                });
+        });
+}
+
+/// Types the name and saves the high score once it is submitted
+#[allow(clippy::too_many_arguments)]
+pub fn name_entry_update(
+    mut commands: Commands,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut entry: ResMut<NameEntry>,
+    mut label: Query<&mut Text, With<NameEntryLabel>>,
+    mut high_scores: ResMut<HighScores>,
+    logic: Res<TetrisLogic>,
+    screen: Query<Entity, With<NameEntryScreen>>,
+    gamepads: Query<&Gamepad>,
+) {
+    // Controllers can't type so save the score without a name
+    let pad_submit = gamepads
+        .iter()
+        .any(|pad| pad.just_pressed(GamepadButton::Start));
+    let pressed_keys = keyboard_events
+        .read()
+        .filter(|event| event.state.is_pressed())
+        .map(|event| event.logical_key.clone());
+
+    for key in pressed_keys.chain(pad_submit.then_some(Key::Enter)) {
+        match &key {
+            Key::Enter => {
+                let name = entry.name.trim();
+                let game = logic.game();
+                let date = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |time| time.as_secs());
+
+                high_scores.insert(
+                    logic.mode(),
+                    HighScore {
+                        name: if name.is_empty() { "Anonymous" } else { name }.to_string(),
+                        score: game.score(),
+                        lines: game.lines(),
+                        level: game.level(),
+                        duration: logic.elapsed(),
+                        date,
+                    },
+                );
+                high_scores.save();
+
+                commands.remove_resource::<NameEntry>();
+                for entity in &screen {
+                    commands.entity(entity).despawn_recursive();
+                }
+                return;
+            }
+            Key::Backspace => {
+                entry.name.pop();
+            }
+            Key::Character(text) => {
+                for c in text.chars().filter(|c| !c.is_control()) {
+                    if entry.name.chars().count() < MAX_NAME_LENGTH {
+                        entry.name.push(c);
+                    }
+                }
+            }
+            Key::Space if entry.name.chars().count() < MAX_NAME_LENGTH => entry.name.push(' '),
+            _ => {}
+        }
+    }
+
+    if entry.is_changed() {
+        **label.single_mut() = format!("Name: {}_", entry.name);
+    }
+}
+
+pub fn name_entry_shutdown(mut commands: Commands) {
+    commands.remove_resource::<NameEntry>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn high_score(name: &str, score: usize) -> HighScore {
+        HighScore {
+            name: name.to_string(),
+            score,
+            lines: 42,
+            level: 5,
+            duration: 123.5,
+            date: 1_700_000_000,
+        }
+    }
+
+    fn table_scores(high_scores: &HighScores, mode: GameMode) -> Vec<usize> {
+        high_scores.get(mode).iter().map(|s| s.score).collect()
+    }
+
+    #[test]
+    fn serialize_round_trips() {
+        let score = high_score("Alex Pajitnov", 12_345);
+
+        let (mode, parsed) = HighScore::parse(&score.serialize(GameMode::Endless)).unwrap();
+        assert_eq!(mode, GameMode::Endless);
+        assert_eq!(parsed.name, score.name);
+        assert_eq!(parsed.score, score.score);
+        assert_eq!(parsed.lines, score.lines);
+        assert_eq!(parsed.level, score.level);
+        assert_eq!(parsed.duration, score.duration);
+        assert_eq!(parsed.date, score.date);
+    }
+
+    #[test]
+    fn invalid_lines_are_rejected() {
+        assert!(HighScore::parse("").is_none());
+        assert!(HighScore::parse("Nonexistent mode\t1\t1\t1\t1\t1\tname").is_none());
+        let line = high_score("name", 100).serialize(GameMode::Endless);
+        assert!(HighScore::parse(&line.replace("\t100\t", "\tlots\t")).is_none());
+        assert!(HighScore::parse(line.rsplit_once('\t').unwrap().0).is_none());
+    }
+
+    #[test]
+    fn insert_keeps_the_table_sorted_and_capped() {
+        let mode = GameMode::Endless;
+        let mut high_scores = HighScores::default();
+        for score in [500, 100, 900, 300, 700, 200, 800, 600, 1000, 400, 50] {
+            high_scores.insert(mode, high_score("name", score));
+        }
+
+        assert_eq!(
+            table_scores(&high_scores, mode),
+            vec![1000, 900, 800, 700, 600, 500, 400, 300, 200, 100]
+        );
+        assert!(table_scores(&high_scores, GameMode::Zen).is_empty());
+    }
+
+    #[test]
+    fn qualifies_until_the_table_is_full_then_above_the_lowest() {
+        let mode = GameMode::Endless;
+        let mut high_scores = HighScores::default();
+        assert!(!high_scores.qualifies(mode, 0));
+
+        for score in 1..=MAX_HIGH_SCORES {
+            assert!(high_scores.qualifies(mode, 100));
+            high_scores.insert(mode, high_score("name", score * 100));
+        }
+        assert!(!high_scores.qualifies(mode, 100));
+        assert!(high_scores.qualifies(mode, 101));
+
+        high_scores.insert(mode, high_score("name", 101));
+        assert_eq!(high_scores.get(mode).len(), MAX_HIGH_SCORES);
+        assert_eq!(high_scores.get(mode).last().unwrap().score, 101);
+    }
+}
@@ -1,25 +1,36 @@
+use crate::game::config::GameConfig;
+use crate::game::high_scores::{
+    name_entry_setup, name_entry_shutdown, name_entry_update, HighScores, NameEntry,
+};
+use crate::game::replay::{replay_setup, replay_shutdown, replay_update, save_replay};
+use crate::game::sprint::{
+    sprint_setup, sprint_shutdown, sprint_update, update_sprint_label, SprintRecords, SprintRun,
+};
+use crate::game::survival::{
+    garbage_ticker_pause, garbage_ticker_resume, survival_setup, survival_shutdown,
+    survival_update, update_survival_meter, GarbageTicker,
+};
 use crate::game::tetris_logic::{ticker_pause, ticker_resume};
-use crate::game::ui::update_labels;
+use crate::game::ui::{update_hold, update_instructions, update_labels, update_previews};
 use bevy::app::{App, Plugin};
 use bevy::prelude::*;
 
 use crate::game::ui_setup::{ui_resize_handler, ui_setup};
+use crate::settings::controls::{Control, ControlState};
+use crate::settings::{Settings, SettingsState};
 use crate::util::despawn_screen;
 use crate::GameState;
 
-mod shapes;
+pub mod config;
+pub mod high_scores;
+pub mod replay;
+pub mod sprint;
+mod survival;
 mod tetris_board;
 mod tetris_logic;
 mod ui;
 mod ui_setup;
 
-pub const BOARD_WIDTH: usize = 10;
-pub const BOARD_HEIGHT: usize = 24;
-pub const BOARD_WIDTH_F: f32 = BOARD_WIDTH as f32;
-pub const BOARD_HEIGHT_F: f32 = BOARD_HEIGHT as f32;
-
-pub const RATIO: f32 = BOARD_WIDTH_F / BOARD_HEIGHT_F;
-
 #[derive(Component)]
 pub struct OnGameScreen;
 
@@ -33,10 +44,13 @@ pub enum InGameState {
 }
 
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
+#[allow(clippy::enum_variant_names)]
 pub enum GameOver {
     #[default]
     NotOver,
     GameOver,
+    /// The goal of the mode was reached
+    Finished,
 }
 
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
@@ -51,57 +65,175 @@ pub struct Score {
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.init_state::<InGameState>()
+        app.init_resource::<GameConfig>()
+            .insert_resource(HighScores::load())
+            .insert_resource(SprintRecords::load())
+            .init_state::<InGameState>()
             .init_state::<GameOver>()
             .init_state::<Difficulty>()
             .init_state::<Score>()
             .add_systems(
                 OnEnter(GameState::Game),
                 (
+                    tetris_logic::tetris_logic_setup,
+                    sprint_setup,
+                    survival_setup,
                     ui_setup,
                     tetris_board::tetris_board_setup,
-                    tetris_logic::tetris_logic_setup,
                     game_setup,
-                ),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                OnEnter(GameState::Replay),
+                (
+                    replay_setup,
+                    ui_setup,
+                    tetris_board::tetris_board_setup,
+                    game_setup,
+                )
+                    .chain(),
             )
             .add_systems(
                 Update,
                 (
                     ui_resize_handler,
                     tetris_board::cell_resize_handler,
-                    pause,
                     update_labels,
+                    update_previews,
+                    update_hold,
                 )
-                    .run_if(in_state(GameState::Game)),
+                    .run_if(in_state(GameState::Game).or(in_state(GameState::Replay))),
+            )
+            .add_systems(
+                Update,
+                update_instructions
+                    .run_if(in_state(GameState::Game))
+                    .run_if(resource_changed::<Settings>),
+            )
+            .add_systems(
+                Update,
+                pause
+                    .run_if(in_state(GameState::Game))
+                    .run_if(in_state(SettingsState::Closed)),
             )
             .add_systems(
                 Update,
                 (pause_menu)
                     .run_if(in_state(GameState::Game))
-                    .run_if(in_state(InGameState::Paused)),
+                    .run_if(in_state(InGameState::Paused))
+                    .run_if(in_state(SettingsState::Closed))
+                    .run_if(not(resource_exists::<NameEntry>)),
+            )
+            .add_systems(
+                OnEnter(GameOver::GameOver),
+                name_entry_setup.run_if(in_state(GameState::Game)),
+            )
+            .add_systems(
+                OnEnter(GameOver::Finished),
+                name_entry_setup.run_if(in_state(GameState::Game)),
+            )
+            .add_systems(
+                Update,
+                name_entry_update
+                    .run_if(in_state(GameState::Game))
+                    .run_if(resource_exists::<NameEntry>)
+                    .after(pause_menu),
             )
             .add_systems(
                 Update,
                 (tetris_logic::tetris_logic_update)
                     .run_if(in_state(GameState::Game))
-                    .run_if(in_state(InGameState::UnPaused)),
+                    .run_if(in_state(InGameState::UnPaused))
+                    .before(tetris_logic::tetris_logic_sync),
+            )
+            .add_systems(
+                Update,
+                tetris_logic::tetris_logic_handling
+                    .run_if(in_state(GameState::Game))
+                    .run_if(resource_changed::<Settings>)
+                    .before(tetris_logic::tetris_logic_update),
+            )
+            .add_systems(
+                Update,
+                replay_update
+                    .run_if(in_state(GameState::Replay))
+                    .before(tetris_logic::tetris_logic_sync),
+            )
+            .add_systems(
+                Update,
+                tetris_logic::tetris_logic_sync
+                    .run_if(in_state(GameState::Game).or(in_state(GameState::Replay))),
+            )
+            .add_systems(
+                Update,
+                sprint_update
+                    .run_if(in_state(GameState::Game))
+                    .run_if(in_state(InGameState::UnPaused))
+                    .run_if(in_state(GameOver::NotOver))
+                    .run_if(resource_exists::<SprintRun>)
+                    .after(tetris_logic::tetris_logic_sync),
+            )
+            .add_systems(
+                Update,
+                update_sprint_label
+                    .run_if(in_state(GameState::Game))
+                    .run_if(resource_exists::<SprintRun>)
+                    .after(sprint_update),
+            )
+            .add_systems(
+                Update,
+                survival_update
+                    .run_if(in_state(GameState::Game))
+                    .run_if(in_state(InGameState::UnPaused))
+                    .run_if(in_state(GameOver::NotOver))
+                    .run_if(resource_exists::<GarbageTicker>)
+                    .after(tetris_logic::tetris_logic_update)
+                    .before(tetris_logic::tetris_logic_sync),
+            )
+            .add_systems(
+                Update,
+                update_survival_meter
+                    .run_if(in_state(GameState::Game))
+                    .run_if(resource_exists::<GarbageTicker>),
             )
             .add_systems(
                 OnEnter(InGameState::UnPaused),
-                (ticker_resume).run_if(in_state(GameState::Game)),
+                (
+                    ticker_resume,
+                    garbage_ticker_resume.run_if(resource_exists::<GarbageTicker>),
+                )
+                    .run_if(in_state(GameState::Game)),
             )
             .add_systems(
                 OnEnter(InGameState::Paused),
-                (ticker_pause).run_if(in_state(GameState::Game)),
+                (
+                    ticker_pause,
+                    garbage_ticker_pause.run_if(resource_exists::<GarbageTicker>),
+                )
+                    .run_if(in_state(GameState::Game)),
             )
             .add_systems(
                 OnExit(GameState::Game),
                 (
+                    save_replay,
+                    name_entry_shutdown,
+                    sprint_shutdown,
+                    survival_shutdown,
                     despawn_screen::<OnGameScreen>,
                     tetris_board::tetris_board_shutdown,
                     tetris_logic::tetris_logic_shutdown,
                 ),
             )
+            .add_systems(
+                OnExit(GameState::Replay),
+                (
+                    despawn_screen::<OnGameScreen>,
+                    tetris_board::tetris_board_shutdown,
+                    tetris_logic::tetris_logic_shutdown,
+                    replay_shutdown,
+                ),
+            )
             .add_systems(OnEnter(GameState::ReloadGame), reload_game);
     }
 }
@@ -111,16 +243,16 @@ fn reload_game(mut game_state: ResMut<NextState<GameState>>) {
 }
 
 fn pause(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    controls: Res<ControlState>,
     game_over: Res<State<GameOver>>,
     state: Res<State<InGameState>>,
     mut next_state: ResMut<NextState<InGameState>>,
 ) {
-    if matches!(game_over.as_ref().get(), GameOver::GameOver) {
+    if !matches!(game_over.as_ref().get(), GameOver::NotOver) {
         return;
     }
 
-    if keyboard_input.just_pressed(KeyCode::Escape) {
+    if controls.just_pressed(Control::Pause) {
         match state.get() {
             InGameState::UnPaused => next_state.set(InGameState::Paused),
             InGameState::Paused => next_state.set(InGameState::UnPaused),
@@ -140,11 +272,21 @@ fn game_setup(
     score_state.set(Score::default());
 }
 
-fn pause_menu(keyboard: Res<ButtonInput<KeyCode>>, mut game_state: ResMut<NextState<GameState>>) {
-    if keyboard.pressed(KeyCode::Enter) {
+fn pause_menu(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    controls: Res<ControlState>,
+    gamepads: Query<&Gamepad>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut settings_state: ResMut<NextState<SettingsState>>,
+) {
+    let pad_pressed = |button| gamepads.iter().any(|pad| pad.just_pressed(button));
+
+    if keyboard.just_pressed(KeyCode::Enter) || pad_pressed(GamepadButton::East) {
         game_state.set(GameState::Menu);
-    } else if keyboard.pressed(KeyCode::KeyR) {
+    } else if controls.just_pressed(Control::Restart) {
         game_state.set(GameState::ReloadGame);
+    } else if keyboard.just_pressed(KeyCode::KeyS) || pad_pressed(GamepadButton::North) {
+        settings_state.set(SettingsState::Open);
     }
 }
 
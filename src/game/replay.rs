@@ -0,0 +1,200 @@
+use crate::game::config::GameMode;
+use crate::game::tetris_logic::TetrisLogic;
+use crate::settings::Settings;
+use crate::util::{data_dir, format_duration};
+use crate::GameState;
+use bevy::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use t_tetris_engine::replay::Replay;
+
+const MIN_SPEED: f32 = 0.25;
+const MAX_SPEED: f32 = 8.0;
+/// Seconds of replay advanced by a frame step
+const FRAME_STEP: f32 = 1.0 / 60.0;
+
+/// Replay chosen in the menu to be played back
+#[derive(Resource)]
+pub struct SelectedReplay(pub Replay);
+
+/// Progress through the replay being played back
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    replay: Replay,
+    /// Index of the next event to apply
+    cursor: usize,
+    /// Milliseconds of the replay played
+    time: f32,
+    speed: f32,
+    paused: bool,
+}
+
+impl ReplayPlayback {
+    fn new(replay: Replay) -> ReplayPlayback {
+        ReplayPlayback {
+            replay,
+            cursor: 0,
+            time: 0.0,
+            speed: 1.0,
+            paused: false,
+        }
+    }
+
+    fn finished(&self) -> bool {
+        self.cursor >= self.replay.events.len()
+    }
+
+    /// Text describing the playback for the sidebar
+    pub fn status(&self) -> String {
+        let state = if self.finished() {
+            "Finished"
+        } else if self.paused {
+            "Paused"
+        } else {
+            "Playing"
+        };
+        let duration = self.replay.duration() as f32 / 1000.0;
+
+        format!(
+            "Replay {state} ({}x)\n{} / {}\n[Pause : Space, Speed : Up/Down, Step : Right, Main Menu : Esc]",
+            self.speed,
+            format_duration((self.time / 1000.0).min(duration)),
+            format_duration(duration),
+        )
+    }
+}
+
+/// Directory replays are saved in
+fn replay_dir() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("replays"))
+}
+
+/// Saves the replay of the game that was just played
+pub fn save_replay(logic: Res<TetrisLogic>) {
+    let replay = logic.replay();
+    if replay.events.is_empty() {
+        return;
+    }
+    let Some(dir) = replay_dir() else {
+        warn!("No data directory to save replays in");
+        return;
+    };
+
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_millis());
+    let path = dir.join(format!("{time}.replay"));
+
+    if let Err(e) = fs::create_dir_all(&dir).and_then(|_| fs::write(&path, replay.serialize())) {
+        warn!("Failed to save replay to {}: {e}", path.display());
+    }
+}
+
+/// Seconds since the unix epoch a replay was saved at, from its file name
+fn saved_at(path: &Path) -> Option<u64> {
+    let millis: u64 = path.file_stem()?.to_str()?.parse().ok()?;
+    Some(millis / 1000)
+}
+
+/// Up to `limit` of the newest saved replays that could be read, newest first, with the
+/// seconds since the unix epoch they were saved at if known. Only reads as many files as
+/// it needs to.
+pub fn saved_replays(limit: usize) -> Vec<(Option<u64>, Replay)> {
+    let Some(entries) = replay_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<(Option<u64>, PathBuf)> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "replay"))
+        .map(|path| (saved_at(&path), path))
+        .collect();
+    paths.sort_by(|a, b| b.cmp(a));
+
+    paths
+        .into_iter()
+        .filter_map(|(saved_at, path)| {
+            let text = fs::read_to_string(&path).ok()?;
+            match Replay::parse(&text) {
+                Ok(replay) => Some((saved_at, replay)),
+                Err(e) => {
+                    warn!("Skipping {}: {e}", path.display());
+                    None
+                }
+            }
+        })
+        .take(limit)
+        .collect()
+}
+
+pub fn replay_setup(
+    mut commands: Commands,
+    selected: Res<SelectedReplay>,
+    settings: Res<Settings>,
+) {
+    let replay = &selected.0;
+    let mode = GameMode::ALL
+        .into_iter()
+        .find(|mode| mode.name() == replay.mode)
+        .unwrap_or_default();
+
+    commands.insert_resource(TetrisLogic::new(
+        mode,
+        &replay.rules,
+        &settings.handling,
+        replay.seed,
+    ));
+    commands.insert_resource(ReplayPlayback::new(selected.0.clone()));
+}
+
+pub fn replay_update(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    mut playback: ResMut<ReplayPlayback>,
+    mut logic: ResMut<TetrisLogic>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard.just_pressed(KeyCode::Escape) || keyboard.just_pressed(KeyCode::Enter) {
+        game_state.set(GameState::Menu);
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::Space) {
+        playback.paused = !playback.paused;
+    }
+    if keyboard.just_pressed(KeyCode::ArrowUp) {
+        playback.speed = (playback.speed * 2.0).min(MAX_SPEED);
+    }
+    if keyboard.just_pressed(KeyCode::ArrowDown) {
+        playback.speed = (playback.speed / 2.0).max(MIN_SPEED);
+    }
+
+    let delta = if !playback.paused {
+        time.delta_secs() * playback.speed
+    } else if keyboard.just_pressed(KeyCode::ArrowRight) {
+        FRAME_STEP
+    } else {
+        return;
+    };
+    if playback.finished() {
+        return;
+    }
+
+    let playback = playback.as_mut();
+    playback.time += delta * 1000.0;
+    // Keeps clocks such as the Ultra countdown in step with the playback
+    logic.advance(delta);
+
+    while let Some(event) = playback.replay.events.get(playback.cursor) {
+        if event.time as f32 > playback.time {
+            break;
+        }
+        logic.play(event);
+        playback.cursor += 1;
+    }
+}
+
+pub fn replay_shutdown(mut commands: Commands) {
+    commands.remove_resource::<ReplayPlayback>();
+    commands.remove_resource::<SelectedReplay>();
+}
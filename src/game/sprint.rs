@@ -0,0 +1,311 @@
+use crate::game::config::GameMode;
+use crate::game::tetris_logic::TetrisLogic;
+use crate::game::ui_setup::ModeLabel;
+use crate::game::OnGameScreen;
+use crate::loading::GlobalFont;
+use crate::settings::controls::Control;
+use crate::settings::Settings;
+use crate::util::{data_dir, format_precise_duration};
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Lines between splits
+pub const SPLIT_LINES: usize = 10;
+
+/// Fastest time for one Sprint goal
+#[derive(Clone, Debug)]
+pub struct SprintRecord {
+    /// Seconds taken to clear the goal
+    pub time: f32,
+    /// Seconds taken to reach every `SPLIT_LINES` lines
+    pub splits: Vec<f32>,
+}
+
+impl SprintRecord {
+    /// Tab separated goal, time and comma separated splits
+    fn serialize(&self, goal: usize) -> String {
+        let splits: Vec<String> = self.splits.iter().map(|split| split.to_string()).collect();
+        format!("{goal}\t{}\t{}", self.time, splits.join(","))
+    }
+
+    fn parse(line: &str) -> Option<(usize, SprintRecord)> {
+        let mut fields = line.splitn(3, '\t');
+        let goal = fields.next()?.parse().ok()?;
+        let time = fields.next()?.parse().ok()?;
+        let splits = match fields.next()? {
+            "" => Vec::new(),
+            splits => splits
+                .split(',')
+                .map(|split| split.parse().ok())
+                .collect::<Option<_>>()?,
+        };
+
+        Some((goal, SprintRecord { time, splits }))
+    }
+}
+
+/// Personal best for each Sprint goal
+#[derive(Resource, Default)]
+pub struct SprintRecords {
+    records: HashMap<usize, SprintRecord>,
+}
+
+impl SprintRecords {
+    fn path() -> Option<PathBuf> {
+        data_dir().map(|dir| dir.join("sprint_records.txt"))
+    }
+
+    /// Loads the saved personal bests, starting without any if there aren't any
+    pub fn load() -> SprintRecords {
+        let mut records = SprintRecords::default();
+        let Some(text) = Self::path().and_then(|path| fs::read_to_string(path).ok()) else {
+            return records;
+        };
+
+        for line in text.lines() {
+            match SprintRecord::parse(line) {
+                Some((goal, record)) => {
+                    records.insert(goal, record);
+                }
+                None => warn!("Skipping invalid sprint record '{line}'"),
+            }
+        }
+
+        records
+    }
+
+    pub fn save(&self) {
+        let Some(path) = Self::path() else {
+            warn!("No data directory to save sprint records in");
+            return;
+        };
+
+        let text: String = self
+            .records
+            .iter()
+            .map(|(goal, record)| record.serialize(*goal) + "\n")
+            .collect();
+
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, text));
+        if let Err(e) = result {
+            warn!("Failed to save sprint records to {}: {e}", path.display());
+        }
+    }
+
+    pub fn get(&self, goal: usize) -> Option<&SprintRecord> {
+        self.records.get(&goal)
+    }
+
+    /// Keeps `record` if it is faster than the personal best for `goal`, returning whether it
+    /// was
+    pub fn insert(&mut self, goal: usize, record: SprintRecord) -> bool {
+        if self.get(goal).is_some_and(|best| best.time <= record.time) {
+            return false;
+        }
+        self.records.insert(goal, record);
+        true
+    }
+}
+
+/// Splits of the Sprint being played
+#[derive(Resource)]
+pub struct SprintRun {
+    goal: usize,
+    splits: Vec<f32>,
+    /// Splits of the personal best when the run started, kept as finishing can replace it
+    best_splits: Vec<f32>,
+}
+
+pub fn sprint_setup(mut commands: Commands, logic: Res<TetrisLogic>, records: Res<SprintRecords>) {
+    if let GameMode::Sprint(goal) = logic.mode() {
+        commands.insert_resource(SprintRun {
+            goal,
+            splits: Vec::new(),
+            best_splits: records
+                .get(goal)
+                .map_or_else(Vec::new, |best| best.splits.clone()),
+        });
+    }
+}
+
+/// Records splits, and the time once the goal is cleared
+pub fn sprint_update(
+    mut commands: Commands,
+    mut run: ResMut<SprintRun>,
+    logic: Res<TetrisLogic>,
+    mut records: ResMut<SprintRecords>,
+    font: Res<GlobalFont>,
+    settings: Res<Settings>,
+) {
+    let lines = logic.game().lines().min(run.goal);
+    while (run.splits.len() + 1) * SPLIT_LINES <= lines {
+        run.splits.push(logic.elapsed());
+    }
+
+    // `tetris_logic_sync` ends the game
+    if lines < run.goal {
+        return;
+    }
+
+    let time = logic.elapsed();
+    let best = records.get(run.goal).map(|best| best.time);
+    let new_best = records.insert(
+        run.goal,
+        SprintRecord {
+            time,
+            splits: run.splits.clone(),
+        },
+    );
+    if new_best {
+        records.save();
+    }
+
+    let best_text = match best {
+        _ if new_best => "New personal best!".to_string(),
+        Some(best) => format!("Personal best: {}", format_precise_duration(best)),
+        None => String::new(),
+    };
+    spawn_finish_screen(
+        &mut commands,
+        &font,
+        &[
+            (format!("Sprint {} finished", run.goal), 40.0),
+            (format_precise_duration(time), 40.0),
+            (best_text, 25.0),
+            (
+                format!(
+                    "[Restart : {}, Main Menu : Return]",
+                    settings.controls.key_names(Control::Restart)
+                ),
+                20.0,
+            ),
+        ],
+    );
+}
+
+fn spawn_finish_screen(commands: &mut Commands, font: &GlobalFont, lines: &[(String, f32)]) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            OnGameScreen,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        padding: UiRect::all(Val::Px(30.0)),
+                        row_gap: Val::Px(15.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.15, 0.15, 0.15)),
+                ))
+                .with_children(|parent| {
+                    for (text, font_size) in lines.iter().filter(|(text, _)| !text.is_empty()) {
+                        parent.spawn((
+                            Text(text.clone()),
+                            TextFont {
+                                font: font.get(),
+                                font_size: *font_size,
+                                ..default()
+                            },
+                            TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                        ));
+                    }
+                });
+        });
+}
+
+/// Shows the timer and the splits so far, with how far ahead or behind the personal best
+/// each one was
+pub fn update_sprint_label(
+    mut label: Query<&mut TextSpan, With<ModeLabel>>,
+    run: Res<SprintRun>,
+    logic: Res<TetrisLogic>,
+) {
+    let mut text = format!("Time: {}", format_precise_duration(logic.elapsed()));
+
+    for (i, split) in run.splits.iter().enumerate() {
+        text += &format!(
+            "\n{:>3}: {}",
+            (i + 1) * SPLIT_LINES,
+            format_precise_duration(*split)
+        );
+        if let Some(best_split) = run.best_splits.get(i) {
+            let difference = split - best_split;
+            let sign = if difference < 0.0 { '-' } else { '+' };
+            text += &format!(" ({sign}{:.3})", difference.abs());
+        }
+    }
+
+    **label.single_mut() = text;
+}
+
+pub fn sprint_shutdown(mut commands: Commands) {
+    commands.remove_resource::<SprintRun>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(time: f32) -> SprintRecord {
+        SprintRecord {
+            time,
+            splits: vec![time / 4.0, time / 2.0, time * 0.75],
+        }
+    }
+
+    #[test]
+    fn serialize_round_trips() {
+        for record in [
+            record(61.25),
+            SprintRecord {
+                time: 9.5,
+                splits: Vec::new(),
+            },
+        ] {
+            let (goal, parsed) = SprintRecord::parse(&record.serialize(40)).unwrap();
+            assert_eq!(goal, 40);
+            assert_eq!(parsed.time, record.time);
+            assert_eq!(parsed.splits, record.splits);
+        }
+    }
+
+    #[test]
+    fn invalid_lines_are_rejected() {
+        assert!(SprintRecord::parse("").is_none());
+        assert!(SprintRecord::parse("40\t61.25").is_none());
+        assert!(SprintRecord::parse("forty\t61.25\t").is_none());
+        assert!(SprintRecord::parse("40\t61.25\t15,x").is_none());
+    }
+
+    #[test]
+    fn insert_keeps_only_faster_times() {
+        let mut records = SprintRecords::default();
+        assert!(records.insert(40, record(60.0)));
+        assert!(!records.insert(40, record(75.0)));
+        assert!(!records.insert(40, record(60.0)));
+        assert_eq!(records.get(40).unwrap().time, 60.0);
+
+        assert!(records.insert(40, record(55.0)));
+        assert_eq!(records.get(40).unwrap().time, 55.0);
+        assert_eq!(records.get(40).unwrap().splits, record(55.0).splits);
+
+        assert!(records.insert(20, record(80.0)));
+        assert_eq!(records.get(40).unwrap().time, 55.0);
+    }
+}
@@ -0,0 +1,80 @@
+use crate::game::config::GameMode;
+use crate::game::tetris_logic::{TetrisLogic, Ticker};
+use crate::game::ui_setup::ModeLabel;
+use bevy::prelude::*;
+
+/// Seconds between garbage rising at the start of a game
+const START_INTERVAL: f32 = 8.0;
+/// Shortest time between garbage rising
+const MIN_INTERVAL: f32 = 2.0;
+/// Seconds taken off the interval for every second survived
+const ACCELERATION: f32 = 0.05;
+/// Seconds survived before another row rises each time, up to `MAX_ROWS`
+const ROWS_INCREASE_TIME: f32 = 60.0;
+const MAX_ROWS: usize = 4;
+/// Characters in the warning meter
+const METER_WIDTH: usize = 10;
+
+/// Times garbage rising in Survival mode. Works like the gravity `Ticker`, including freezing
+/// while paused.
+#[derive(Resource)]
+pub struct GarbageTicker(Ticker);
+
+/// Seconds between garbage rising after surviving `elapsed` seconds
+fn interval(elapsed: f32) -> f32 {
+    (START_INTERVAL - elapsed * ACCELERATION).max(MIN_INTERVAL)
+}
+
+/// Rows rising at once after surviving `elapsed` seconds
+fn rows(elapsed: f32) -> usize {
+    (1 + (elapsed / ROWS_INCREASE_TIME) as usize).min(MAX_ROWS)
+}
+
+pub fn survival_setup(mut commands: Commands, time: Res<Time>, logic: Res<TetrisLogic>) {
+    if matches!(logic.mode(), GameMode::Survival) {
+        commands.insert_resource(GarbageTicker(Ticker::new(&time, interval(0.0))));
+    }
+}
+
+pub fn survival_update(
+    mut logic: ResMut<TetrisLogic>,
+    mut ticker: ResMut<GarbageTicker>,
+    time: Res<Time>,
+) {
+    for _ in 0..ticker.0.ticks(&time) {
+        let rows = rows(logic.elapsed());
+        logic.add_garbage(rows);
+    }
+
+    ticker.0.set_interval(interval(logic.elapsed()));
+}
+
+pub fn garbage_ticker_pause(mut ticker: ResMut<GarbageTicker>, time: Res<Time>) {
+    ticker.0.pause(&time);
+}
+
+pub fn garbage_ticker_resume(mut ticker: ResMut<GarbageTicker>, time: Res<Time>) {
+    ticker.0.resume(&time);
+}
+
+/// Warning meter filling up as the next garbage gets closer
+pub fn update_survival_meter(
+    mut label: Query<&mut TextSpan, With<ModeLabel>>,
+    ticker: Res<GarbageTicker>,
+    logic: Res<TetrisLogic>,
+    time: Res<Time>,
+) {
+    let filled = (ticker.0.progress(&time) * METER_WIDTH as f32) as usize;
+    let rows = rows(logic.elapsed());
+
+    **label.single_mut() = format!(
+        "Incoming: {rows} row{}\n[{}{}]",
+        if rows == 1 { "" } else { "s" },
+        "#".repeat(filled),
+        ".".repeat(METER_WIDTH - filled)
+    );
+}
+
+pub fn survival_shutdown(mut commands: Commands) {
+    commands.remove_resource::<GarbageTicker>();
+}
@@ -1,15 +1,18 @@
+use crate::game::tetris_logic::TetrisLogic;
 use crate::game::ui_setup::get_target_and_sidebar_width;
-use crate::game::{OnGameScreen, BOARD_HEIGHT, BOARD_HEIGHT_F, BOARD_WIDTH, BOARD_WIDTH_F};
+use crate::game::OnGameScreen;
 use bevy::asset::{Assets, Handle};
 use bevy::color::palettes::css;
 use bevy::math::Vec2;
 use bevy::prelude::{
-    default, Color, ColorMaterial, Commands, Component, EventReader, Mesh, MeshMaterial2d, Query,
-    Rectangle, ResMut, Resource, Transform, Window,
+    Color, ColorMaterial, Commands, Component, EventReader, Mesh, Mesh2d, MeshMaterial2d, Mix,
+    Query, Rectangle, Res, ResMut, Resource, Transform, Window,
 };
-use bevy::sprite::MaterialMesh2dBundle;
 use bevy::window::WindowResized;
-use std::array;
+use t_tetris_engine::{Colors, Game};
+
+/// Fraction of the lowest hidden row drawn above the board
+const BUFFER_ROW_PEEK: f32 = 0.5;
 
 #[derive(Component)]
 pub struct TetrisCell {
@@ -30,135 +33,177 @@ impl TetrisCell {
 pub fn cell_resize_handler(
     mut resize_events: EventReader<WindowResized>,
     mut cells: Query<(&mut Transform, &TetrisCell)>,
+    logic: Res<TetrisLogic>,
 ) {
     let Some(e) = resize_events.read().last() else {
         return;
     };
-    let cell_width = get_cell_width(e.width, e.height);
+    let game = logic.game();
+    let cell_width = get_cell_width(e.width, e.height, game);
 
     for (mut transform, cell) in cells.iter_mut() {
         let (x, y) = cell.location();
-        let pos = get_cell_pos(cell_width, x, y);
+        let pos = get_cell_pos(cell_width, game, x, y);
 
         transform.translation = pos.extend(0.0);
-        transform.scale = Vec2::splat(cell_width).extend(0.0);
+        transform.scale = get_cell_size(cell_width, game, y).extend(0.0);
     }
 }
 
-fn get_cell_width(width: f32, height: f32) -> f32 {
-    let (target_width, _) = get_target_and_sidebar_width(width, height);
-    target_width / BOARD_WIDTH_F
+/// Rows drawn - the visible rows and, if there are hidden rows above them, the lowest hidden
+/// row so players can see tetrominos spawning and blocks overflowing
+fn shown_rows(game: &Game) -> usize {
+    (game.visible_height() + 1).min(game.board().height())
 }
 
-fn get_cell_pos(cell_width: f32, x: usize, y: usize) -> Vec2 {
+/// Height in cells of row `y` as drawn, the lowest hidden row only peeking above the board
+fn row_height(game: &Game, y: usize) -> f32 {
+    if y < game.visible_height() {
+        1.0
+    } else {
+        BUFFER_ROW_PEEK
+    }
+}
+
+/// Height in cells of the board as drawn
+pub fn shown_height(game: &Game) -> f32 {
+    (0..shown_rows(game)).map(|y| row_height(game, y)).sum()
+}
+
+fn get_cell_width(width: f32, height: f32, game: &Game) -> f32 {
+    let (target_width, _) = get_target_and_sidebar_width(width, height, game);
+    target_width / game.board().width() as f32
+}
+
+fn get_cell_pos(cell_width: f32, game: &Game, x: usize, y: usize) -> Vec2 {
     Vec2::new(
-        (x as f32 - (BOARD_WIDTH_F / 2.0)) * cell_width + (cell_width / 2.0),
-        (y as f32 - (BOARD_HEIGHT_F / 2.0)) * cell_width + (cell_width / 2.0),
+        (x as f32 - (game.board().width() as f32 / 2.0)) * cell_width + (cell_width / 2.0),
+        (y as f32 + row_height(game, y) / 2.0 - shown_height(game) / 2.0) * cell_width,
     )
 }
 
-#[derive(Copy, Clone)]
-pub enum Colors {
-    Empty,
-    LightBlue,
-    Blue,
-    Orange,
-    Yellow,
-    Lime,
-    Purple,
-    Red,
+fn get_cell_size(cell_width: f32, game: &Game, y: usize) -> Vec2 {
+    Vec2::new(cell_width, cell_width * row_height(game, y))
 }
 
-// impl Colors {
-//     pub fn random_not_empty() -> Colors {
-//         match thread_rng().gen_range(0..3) {
-//             0 => Colors::Red,
-//             1 => Colors::Lime,
-//             2 => Colors::Blue,
-//             _ => unreachable!()
-//         }
-//     }
-// }
-
-impl Colors {
-    fn get_color(&self) -> Color {
-        match &self {
-            Colors::Empty => Color::BLACK,
-            Colors::Red => css::RED.into(),
-            Colors::Lime => css::LIME.into(),
-            Colors::Blue => css::BLUE.into(),
-            Colors::LightBlue => css::TEAL.into(),
-            Colors::Orange => css::ORANGE.into(),
-            Colors::Yellow => css::YELLOW.into(),
-            Colors::Purple => css::PURPLE.into(),
-        }
+/// Colour used to draw a cell of the board
+pub fn cell_color(color: Colors) -> Color {
+    match color {
+        Colors::Empty => Color::BLACK,
+        Colors::Red => css::RED.into(),
+        Colors::Lime => css::LIME.into(),
+        Colors::Blue => css::BLUE.into(),
+        Colors::LightBlue => css::TEAL.into(),
+        Colors::Orange => css::ORANGE.into(),
+        Colors::Yellow => css::YELLOW.into(),
+        Colors::Purple => css::PURPLE.into(),
+        Colors::Garbage => css::GRAY.into(),
     }
 }
 
+/// Dimmed colour used to draw the ghost piece on the empty (black) board
+pub fn ghost_color(color: Colors) -> Color {
+    cell_color(Colors::Empty).mix(&cell_color(color), 0.35)
+}
+
+/// Cells drawn on screen, kept in sync with a `Game`
 #[derive(Resource)]
 pub struct TetrisBoard {
-    board_materials: [[Handle<ColorMaterial>; BOARD_HEIGHT]; BOARD_WIDTH],
-    board: [[Colors; BOARD_HEIGHT]; BOARD_WIDTH],
+    board_materials: Vec<Vec<Handle<ColorMaterial>>>,
+    shown: Vec<Vec<Color>>,
 }
 
 impl TetrisBoard {
+    /// Spawns a cell for every drawn cell of `game`'s board, sized to fit a `width` x `height`
+    /// window
     pub fn create(
         width: f32,
         height: f32,
+        game: &Game,
         commands: &mut Commands,
         meshes: &mut ResMut<Assets<Mesh>>,
         materials: &mut ResMut<Assets<ColorMaterial>>,
     ) -> TetrisBoard {
-        let cell_width = get_cell_width(width, height);
-
-        let board_materials = array::from_fn(|x| {
-            array::from_fn(|y| {
-                // let i = y * BOARD_WIDTH + x;
+        let cell_width = get_cell_width(width, height, game);
+        let (columns, rows) = (game.board().width(), shown_rows(game));
 
-                let pos = get_cell_pos(cell_width, x, y);
+        let mut board_materials = Vec::with_capacity(columns);
+        for x in 0..columns {
+            let mut column = Vec::with_capacity(rows);
+            for y in 0..rows {
+                let pos = get_cell_pos(cell_width, game, x, y);
 
-                let handle = materials.add(Color::BLACK);
+                let handle = materials.add(cell_color(Colors::Empty));
 
                 commands.spawn((
-                    MaterialMesh2dBundle {
-                        mesh: meshes.add(Rectangle::default()).into(),
-                        material: MeshMaterial2d(handle.clone()),
-                        transform: Transform::from_translation(pos.extend(0.0))
-                            .with_scale(Vec2::splat(cell_width).extend(1.)),
-                        ..default()
-                    },
+                    Mesh2d(meshes.add(Rectangle::default())),
+                    MeshMaterial2d(handle.clone()),
+                    Transform::from_translation(pos.extend(0.0))
+                        .with_scale(get_cell_size(cell_width, game, y).extend(1.)),
                     OnGameScreen,
                     TetrisCell::new(x, y),
                 ));
 
-                handle
-            })
-        });
-
-        let board = [[Colors::Empty; BOARD_HEIGHT]; BOARD_WIDTH];
+                column.push(handle);
+            }
+            board_materials.push(column);
+        }
 
         TetrisBoard {
             board_materials,
-            board,
+            shown: vec![vec![cell_color(Colors::Empty); rows]; columns],
         }
     }
 
-    pub fn set_cell_colour(
+    fn set_cell_colour(
         &mut self,
         x: usize,
         y: usize,
-        color: Colors,
+        color: Color,
         materials: &mut Assets<ColorMaterial>,
     ) {
-        materials
-            .get_mut(&self.board_materials[x][y])
-            .unwrap()
-            .color = color.get_color();
-        self.board[x][y] = color;
+        if self.shown[x][y] != color {
+            materials
+                .get_mut(&self.board_materials[x][y])
+                .unwrap()
+                .color = color;
+            self.shown[x][y] = color;
+        }
     }
 
-    pub fn board(&self) -> &[[Colors; BOARD_HEIGHT]; BOARD_WIDTH] {
-        &self.board
+    /// Draws the locked blocks and tetromino in play of `game`, along with its ghost piece if
+    /// `show_ghost` is set. The ghost piece is only drawn and never part of the game's board.
+    /// Blocks in hidden rows above the lowest one aren't drawn.
+    pub fn sync(&mut self, game: &Game, show_ghost: bool, materials: &mut Assets<ColorMaterial>) {
+        let rows = shown_rows(game);
+        let mut colors: Vec<Vec<Color>> = game
+            .board()
+            .cells()
+            .iter()
+            .map(|column| column[..rows].iter().copied().map(cell_color).collect())
+            .collect();
+        let mut draw = |x: i32, y: i32, color| {
+            if (y as usize) < rows {
+                colors[x as usize][y as usize] = color;
+            }
+        };
+
+        if let Some(piece) = game.piece() {
+            if show_ghost {
+                for (x, y) in game.ghost().unwrap().blocks() {
+                    draw(x, y, ghost_color(piece.shape.get_color()));
+                }
+            }
+            for (x, y) in piece.blocks() {
+                draw(x, y, cell_color(piece.shape.get_color()));
+            }
+        }
+
+        for (x, column) in colors.iter().enumerate() {
+            for (y, color) in column.iter().enumerate() {
+                self.set_cell_colour(x, y, *color, materials);
+            }
+        }
     }
 }
 
@@ -167,11 +212,13 @@ pub fn tetris_board_setup(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     window: Query<&Window>,
+    logic: Res<TetrisLogic>,
 ) {
     let window = window.single();
     let board = TetrisBoard::create(
         window.width(),
         window.height(),
+        logic.game(),
         &mut commands,
         &mut meshes,
         &mut materials,
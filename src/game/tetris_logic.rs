@@ -1,48 +1,19 @@
-use crate::game::shapes::{BACK_L_SHAPE, BACK_Z_SHAPE, LINE, L_SHAPE, SQUARE, T_SHAPE, Z_SHAPE};
-use crate::game::tetris_board::{Colors, TetrisBoard};
-use crate::game::{Difficulty, GameOver, InGameState, Score, BOARD_HEIGHT, BOARD_WIDTH};
+use crate::game::config::{GameConfig, GameMode, DIG_ROWS};
+use crate::game::tetris_board::TetrisBoard;
+use crate::game::{Difficulty, GameOver, InGameState, Score};
+use crate::settings::controls::{Control, ControlState};
+use crate::settings::{Handling, Settings};
 use bevy::asset::Assets;
-use bevy::input::ButtonInput;
 use bevy::prelude::*;
 use rand::random;
-use rand_derive2::RandGen;
-
-#[derive(Debug, RandGen)]
-enum Tetrominos {
-    LShape,
-    BackLShape,
-    Line,
-    Square,
-    ZShape,
-    BackZShape,
-    TShape,
-}
+use t_tetris_engine::handling::{AutoShift, Shift, SoftDrop};
+use t_tetris_engine::replay::{Replay, ReplayAction, ReplayEvent};
+use t_tetris_engine::{Action, Game, Rules};
 
-impl Tetrominos {
-    pub fn get_shape(&self) -> &'static [[[bool; 4]; 4]; 4] {
-        match &self {
-            Tetrominos::LShape => &L_SHAPE,
-            Tetrominos::BackLShape => &BACK_L_SHAPE,
-            Tetrominos::Line => &LINE,
-            Tetrominos::Square => &SQUARE,
-            Tetrominos::ZShape => &Z_SHAPE,
-            Tetrominos::BackZShape => &BACK_Z_SHAPE,
-            Tetrominos::TShape => &T_SHAPE,
-        }
-    }
-
-    pub fn get_color(&self) -> Colors {
-        match &self {
-            Tetrominos::LShape => Colors::Orange,
-            Tetrominos::BackLShape => Colors::Blue,
-            Tetrominos::Line => Colors::LightBlue,
-            Tetrominos::Square => Colors::Yellow,
-            Tetrominos::ZShape => Colors::Red,
-            Tetrominos::BackZShape => Colors::Lime,
-            Tetrominos::TShape => Colors::Purple,
-        }
-    }
-}
+/// Most placed tetrominos that can be undone in a row
+const MAX_UNDO: usize = 100;
+/// Gravity interval soft drop speed is based on when gravity is slower or off
+const MAX_SOFT_DROP_INTERVAL: f32 = 1.0;
 
 #[derive(Resource)]
 pub struct Ticker {
@@ -62,12 +33,22 @@ impl Ticker {
 
     pub fn ticks(&mut self, time: &Time) -> usize {
         let ticks = ((time.elapsed_secs() - self.last) / self.interval) as usize;
+        // Also keeps an infinite interval from making `last` NaN
+        if ticks == 0 {
+            return 0;
+        }
 
         self.last += self.interval * ticks as f32;
 
         ticks
     }
 
+    /// How far through the current interval the ticker is, from 0 to 1
+    pub fn progress(&self, time: &Time) -> f32 {
+        let now = self.save_point.unwrap_or(time.elapsed_secs());
+        ((now - self.last) / self.interval).clamp(0.0, 1.0)
+    }
+
     pub fn set_interval(&mut self, interval: f32) {
         self.interval = interval;
     }
@@ -77,7 +58,7 @@ impl Ticker {
     }
 
     pub fn resume(&mut self, time: &Time) {
-        if let Some(save_point) = self.save_point {
+        if let Some(save_point) = self.save_point.take() {
             self.last = time.elapsed_secs() - (save_point - self.last);
         }
     }
@@ -91,385 +72,554 @@ pub fn ticker_resume(mut ticker: ResMut<Ticker>, time: Res<Time>) {
     ticker.resume(&time);
 }
 
+/// Drives a `Game` from the keyboard or a controller, recording every action applied
 #[derive(Resource)]
 pub struct TetrisLogic {
-    x: i32,
-    y: i32,
-    rot: usize,
-    current_shape: Option<Tetrominos>,
-    current_color: Colors,
-    difficulty: usize,
-    score: usize,
+    game: Game,
+    mode: GameMode,
+    /// DAS, ARR and soft drop factor `auto_shift` and `soft_drop` were built with
+    handling: Handling,
+    auto_shift: AutoShift,
+    soft_drop: SoftDrop,
+    /// Seconds of unpaused play
+    elapsed: f32,
+    replay: Replay,
+    /// Snapshots of the game as each tetromino spawned, latest last, kept in practice modes
+    history: Vec<Game>,
 }
 
 impl TetrisLogic {
-    pub fn new() -> TetrisLogic {
+    /// Starts a game of `mode`, with `rules` already changed for the mode
+    pub fn new(mode: GameMode, rules: &Rules, handling: &Handling, seed: u64) -> TetrisLogic {
+        let mut replay = Replay::new(seed, rules);
+        replay.mode = mode.name();
+        let mut game = Game::new(rules, seed);
+        if let Some(goal) = mode.garbage_goal() {
+            game.add_garbage(goal.min(dig_rows(&game)));
+        }
+
         TetrisLogic {
-            x: 0,
-            y: 0,
-            rot: 0,
-            current_shape: None,
-            current_color: Colors::Red,
-            difficulty: 0,
-            score: 0,
+            game,
+            mode,
+            handling: handling.clone(),
+            auto_shift: AutoShift::new(handling.das, handling.arr),
+            soft_drop: SoftDrop::new(handling.soft_drop_factor),
+            elapsed: 0.0,
+            replay,
+            history: Vec::new(),
         }
     }
 
-    pub fn update(
-        &mut self,
-        board: &mut TetrisBoard,
-        keyboard: &ButtonInput<KeyCode>,
-        materials: &mut Assets<ColorMaterial>,
-    ) {
-        if self.current_shape.is_some() {
-            if keyboard.just_pressed(KeyCode::ArrowDown) {
-                self.down(board, materials);
-            }
-            if keyboard.just_pressed(KeyCode::ArrowUp) {
-                self.snap_down(board, materials);
-            }
-            if keyboard.just_pressed(KeyCode::ArrowLeft) {
-                self.left(board, materials);
-            }
-            if keyboard.just_pressed(KeyCode::ArrowRight) {
-                self.right(board, materials);
-            }
-            if keyboard.just_pressed(KeyCode::KeyE) {
-                self.clockwise(board, materials);
-            }
-            if keyboard.just_pressed(KeyCode::KeyQ) {
-                self.anticlockwise(board, materials);
-            }
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Switches to new DAS, ARR and soft drop settings, e.g. after they are changed while
+    /// paused. Unchanged settings keep the DAS charge.
+    pub fn set_handling(&mut self, handling: &Handling) {
+        if *handling == self.handling {
+            return;
         }
+
+        self.handling = handling.clone();
+        self.auto_shift = AutoShift::new(handling.das, handling.arr);
+        self.soft_drop = SoftDrop::new(handling.soft_drop_factor);
     }
 
-    pub fn tick(
-        &mut self,
-        board: &mut TetrisBoard,
-        materials: &mut Assets<ColorMaterial>,
-        difficulty: &mut NextState<Difficulty>,
-        score: &mut NextState<Score>,
-        ticker: &mut Ticker,
-        in_game_state: &mut NextState<InGameState>,
-        game_over_state: &mut NextState<GameOver>,
-    ) {
-        if self.current_shape.is_none() {
-            if !self.spawn(board, materials) {
-                in_game_state.set(InGameState::Paused);
-                game_over_state.set(GameOver::GameOver);
-            }
-        } else if !self.down(board, materials) {
-            let clears = self.check_clear(board, materials);
-            let score_a = if clears == 0 {
-                0
-            } else {
-                100 * 2usize.pow(clears - 1)
-            };
+    pub fn mode(&self) -> GameMode {
+        self.mode
+    }
 
-            self.score += score_a;
-            score.set(Score { score: self.score });
+    /// Actions applied so far
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
 
-            self.difficulty += clears as usize;
-            difficulty.set(Difficulty {
-                difficulty: self.difficulty,
-            });
+    /// Seconds of unpaused play
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
 
-            ticker.set_interval(self.get_interval());
+    /// Seconds left before the game ends, for modes played against the clock
+    pub fn time_left(&self) -> Option<f32> {
+        self.mode
+            .time_limit()
+            .map(|limit| (limit - self.elapsed).max(0.0))
+    }
 
-            self.current_shape = None;
+    /// Whether the goal of the mode has been reached, clearing its lines or running out its time
+    pub fn finished(&self) -> bool {
+        self.time_left() == Some(0.0)
+            || self
+                .mode
+                .line_goal()
+                .is_some_and(|goal| self.game.lines() >= goal)
+            || self
+                .mode
+                .garbage_goal()
+                .is_some_and(|goal| self.game.garbage_cleared() >= goal)
+    }
 
-            if !self.spawn(board, materials) {
-                in_game_state.set(InGameState::Paused);
-                game_over_state.set(GameOver::GameOver);
-            }
+    /// Advances the time actions are recorded at by `delta` seconds, stopping at the mode's
+    /// time limit
+    pub fn advance(&mut self, delta: f32) {
+        self.elapsed += delta;
+        if let Some(limit) = self.mode.time_limit() {
+            self.elapsed = self.elapsed.min(limit);
         }
     }
 
-    //noinspection ALL
-    fn check_clear(
-        &mut self,
-        board: &mut TetrisBoard,
-        materials: &mut Assets<ColorMaterial>,
-    ) -> u32 {
-        let c_board = board.board();
-        let mut found = None;
+    /// Applies and records `action`. Gravity that can't move the tetromino in play only
+    /// starts its lock delay, which replays don't need as locking is recorded, so it is left
+    /// out to keep replays at high gravity small.
+    fn apply(&mut self, action: Action) -> bool {
+        let piece_gravity = matches!(action, Action::Gravity) && self.game.piece().is_some();
+        let time = (self.elapsed * 1000.0) as u32;
+        let result = self.perform(action);
+        if result || !piece_gravity {
+            self.replay.record(time, action);
+        }
+        result
+    }
 
-        'outer: for y in 0..BOARD_HEIGHT {
-            for x in 0..BOARD_WIDTH {
-                if matches!(c_board[x][y], Colors::Empty) {
-                    continue 'outer;
-                }
+    /// Applies a recorded action without recording it again
+    pub fn play(&mut self, event: &ReplayEvent) {
+        match event.action {
+            ReplayAction::Game(action) => {
+                self.perform(action);
             }
-
-            found = Some(y);
-            break;
+            ReplayAction::Undo => self.undo(),
         }
+    }
 
-        if let Some(found) = found {
-            if found != BOARD_HEIGHT - 1 {
-                for y in found + 1..BOARD_HEIGHT {
-                    for x in 0..BOARD_WIDTH {
-                        let color = board.board()[x][y];
-                        board.set_cell_colour(x, y - 1, color, materials);
-                    }
-                }
+    /// Applies `action` along with the rules of the mode that the game doesn't know about
+    fn perform(&mut self, action: Action) -> bool {
+        let had_piece = self.game.piece().is_some();
+        let placed = self.game.placed();
+        let result = self.game.apply(action);
 
-                1 + self.check_clear(board, materials)
-            } else {
-                1
-            }
-        } else {
-            0
+        if self.game.placed() != placed {
+            self.refill_garbage();
+        }
+        if !self.mode.is_practice() {
+            return result;
         }
-    }
 
-    fn get_interval(&self) -> f32 {
-        1.0 / ((self.difficulty as f32 / 10.0) + 2.0)
-    }
-    fn spawn(&mut self, board: &mut TetrisBoard, materials: &mut Assets<ColorMaterial>) -> bool {
-        if self.current_shape.is_some() {
-            panic!("Tried to spawn tetromino while one is in play!");
+        if self.game.is_over() {
+            self.game.clear_board();
+        }
+        // Snapshot once the next tetromino is in play, which can be later than the lock
+        if self.game.piece().is_some() && (self.game.placed() != placed || !had_piece) {
+            self.history.push(self.game.clone());
+            if self.history.len() > MAX_UNDO + 1 {
+                self.history.remove(0);
+            }
         }
 
-        self.current_shape = Some(random());
-        self.current_color = self.current_shape.as_ref().unwrap().get_color();
-        self.x = ((BOARD_WIDTH / 2) - (4 / 2)) as i32;
-        self.y = (BOARD_HEIGHT - 1) as i32;
-        self.rot = 0;
+        result
+    }
 
-        if !self.test(board) {
-            return false;
+    /// Pushes `rows` rows of garbage up from the bottom of the board
+    pub fn add_garbage(&mut self, rows: usize) {
+        for _ in 0..rows {
+            self.apply(Action::Garbage);
         }
+    }
 
-        self.draw(board, materials);
+    /// Tops the garbage on the board back up to `dig_rows`, or however many are left to dig
+    fn refill_garbage(&mut self) {
+        let Some(goal) = self.mode.garbage_goal() else {
+            return;
+        };
 
-        true
+        let left = goal.saturating_sub(self.game.garbage_cleared());
+        let rows = self.game.board().garbage_rows();
+        self.game
+            .add_garbage(left.min(dig_rows(&self.game)).saturating_sub(rows));
     }
 
-    fn draw(&mut self, board: &mut TetrisBoard, materials: &mut Assets<ColorMaterial>) {
-        let shape = &self.current_shape.as_ref().unwrap().get_shape()[self.rot];
+    /// Restores the game to when the last placed tetromino spawned
+    fn undo(&mut self) {
+        if self.history.len() < 2 {
+            return;
+        }
+
+        self.history.pop();
+        self.game = self.history.last().unwrap().clone();
+    }
 
-        for x in 0..4 {
-            for y in 0..4 {
-                if shape[y][x] {
-                    let true_x = self.x + x as i32;
-                    let true_y = self.y - y as i32;
+    /// Handles player input over the last `delta` seconds
+    pub fn update(&mut self, controls: &ControlState, delta: f32) {
+        // Charge DAS even without a tetromino in play so it carries over to the next one
+        let shift = self.auto_shift.update(
+            controls.pressed(Control::MoveLeft),
+            controls.just_pressed(Control::MoveLeft),
+            controls.pressed(Control::MoveRight),
+            controls.just_pressed(Control::MoveRight),
+            delta,
+        );
+        let drops = self.soft_drop.update(
+            controls.pressed(Control::SoftDrop),
+            controls.just_pressed(Control::SoftDrop),
+            delta,
+            self.game.gravity_interval().min(MAX_SOFT_DROP_INTERVAL),
+        );
 
-                    if true_x >= 0
-                        && true_y >= 0
-                        && true_x < BOARD_WIDTH as i32
-                        && true_y < BOARD_HEIGHT as i32
-                    {
-                        board.set_cell_colour(
-                            true_x as usize,
-                            true_y as usize,
-                            self.current_color,
-                            materials,
-                        );
-                    }
-                }
+        if self.game.piece().is_none() {
+            // Without gravity nothing else spawns the first tetromino
+            if self.game.gravity_interval().is_infinite() && !self.game.spawn_pending() {
+                self.apply(Action::Gravity);
             }
+            return;
         }
-    }
 
-    fn undraw(&mut self, board: &mut TetrisBoard, materials: &mut Assets<ColorMaterial>) {
-        let shape = &self.current_shape.as_ref().unwrap().get_shape()[self.rot];
-
-        for x in 0..4 {
-            for y in 0..4 {
-                if shape[y][x] {
-                    let true_x = self.x + x as i32;
-                    let true_y = self.y - y as i32;
-
-                    if true_x >= 0
-                        && true_y >= 0
-                        && true_x < BOARD_WIDTH as i32
-                        && true_y < BOARD_HEIGHT as i32
-                    {
-                        board.set_cell_colour(
-                            true_x as usize,
-                            true_y as usize,
-                            Colors::Empty,
-                            materials,
-                        );
-                    }
+        if let Some((shift, moves)) = shift {
+            let action = match shift {
+                Shift::Left => Action::MoveLeft,
+                Shift::Right => Action::MoveRight,
+            };
+            for _ in 0..moves {
+                if !self.apply(action) {
+                    break;
                 }
             }
         }
-    }
+        for _ in 0..drops {
+            if !self.apply(Action::SoftDrop) {
+                break;
+            }
+        }
 
-    fn test(&self, board: &TetrisBoard) -> bool {
-        let shape = &self.current_shape.as_ref().unwrap().get_shape()[self.rot];
-
-        for x in 0..4 {
-            for y in 0..4 {
-                if shape[y][x] {
-                    let true_x = self.x + x as i32;
-                    let true_y = self.y - y as i32;
-
-                    if true_x < 0
-                        || true_y < 0
-                        || true_x >= BOARD_WIDTH as i32
-                        || true_y >= BOARD_HEIGHT as i32
-                        || !matches!(
-                            &board.board()[true_x as usize][true_y as usize],
-                            Colors::Empty
-                        )
-                    {
-                        return false;
-                    }
+        let presses = [
+            (Control::HardDrop, Action::HardDrop),
+            (Control::RotateClockwise, Action::RotateClockwise),
+            (Control::RotateAnticlockwise, Action::RotateAnticlockwise),
+            (Control::Rotate180, Action::Rotate180),
+            (Control::Hold, Action::Hold),
+        ];
+        for (control, action) in presses {
+            if controls.just_pressed(control) {
+                self.apply(action);
+                // Later inputs were meant for the tetromino that was dropped
+                if matches!(action, Action::HardDrop) {
+                    return;
                 }
             }
         }
 
-        true
-    }
-
-    fn down(&mut self, board: &mut TetrisBoard, materials: &mut Assets<ColorMaterial>) -> bool {
-        self.undraw(board, materials);
-        self.y -= 1;
-
-        let res = self.test(board);
-        if !res {
-            self.y += 1;
+        if self.mode.is_practice() && controls.just_pressed(Control::Undo) {
+            self.replay
+                .record((self.elapsed * 1000.0) as u32, ReplayAction::Undo);
+            self.undo();
         }
-
-        self.draw(board, materials);
-        res
     }
 
-    fn snap_down(&mut self, board: &mut TetrisBoard, materials: &mut Assets<ColorMaterial>) {
-        while self.down(board, materials) {}
+    /// Applies gravity, spawning a tetromino if there isn't one in play
+    pub fn tick(&mut self) {
+        // `update_spawn` spawns the next tetromino once the delay is over
+        if !self.game.spawn_pending() {
+            self.apply(Action::Gravity);
+        }
     }
 
-    fn left(&mut self, board: &mut TetrisBoard, materials: &mut Assets<ColorMaterial>) -> bool {
-        self.undraw(board, materials);
-        self.x -= 1;
-
-        let res = self.test(board);
-        if !res {
-            self.x += 1;
+    /// Advances the lock delay by `delta` seconds, locking the tetromino if it has run out
+    pub fn update_lock(&mut self, delta: f32) {
+        if self.game.lock_due(delta) {
+            self.apply(Action::Lock);
         }
-
-        self.draw(board, materials);
-        res
     }
 
-    fn right(&mut self, board: &mut TetrisBoard, materials: &mut Assets<ColorMaterial>) -> bool {
-        self.undraw(board, materials);
-        self.x += 1;
-
-        let res = self.test(board);
-        if !res {
-            self.x -= 1;
+    /// Advances the delay before the next tetromino by `delta` seconds, spawning it if it has
+    /// run out. Holding hold or a rotation as it spawns applies them to it if the rules allow
+    /// it.
+    pub fn update_spawn(&mut self, controls: &ControlState, delta: f32) {
+        if !self.game.spawn_due(delta) {
+            return;
         }
 
-        self.draw(board, materials);
-        res
-    }
-
-    fn clockwise(
-        &mut self,
-        board: &mut TetrisBoard,
-        materials: &mut Assets<ColorMaterial>,
-    ) -> bool {
-        self.undraw(board, materials);
-        self.rot = (self.rot + 1) % 4;
-
-        let res = self.test(board);
-        if !res {
-            self.x += 1;
-            if !self.test(board) {
-                self.x -= 2;
-                if !self.test(board) {
-                    self.x += 1;
-                    self.rot = match self.rot {
-                        0 => 3,
-                        r => r - 1,
-                    };
-                }
+        if self.game.initial_actions() {
+            if controls.pressed(Control::Hold) {
+                self.apply(Action::Hold);
             }
-        }
-
-        self.draw(board, materials);
-        res
-    }
-
-    fn anticlockwise(
-        &mut self,
-        board: &mut TetrisBoard,
-        materials: &mut Assets<ColorMaterial>,
-    ) -> bool {
-        self.undraw(board, materials);
-        self.rot = match self.rot {
-            0 => 3,
-            r => r - 1,
-        };
-
-        let res = self.test(board);
-        if !res {
-            if !res {
-                self.x += 1;
-                if !self.test(board) {
-                    self.x -= 2;
-                    if !self.test(board) {
-                        self.x += 1;
-                        self.rot = (self.rot + 1) % 4;
-                    }
-                }
+            let rotations = [
+                (Control::RotateClockwise, Action::RotateClockwise),
+                (Control::RotateAnticlockwise, Action::RotateAnticlockwise),
+                (Control::Rotate180, Action::Rotate180),
+            ];
+            if let Some((_, action)) = rotations
+                .into_iter()
+                .find(|(control, _)| controls.pressed(*control))
+            {
+                self.apply(action);
             }
         }
-
-        self.draw(board, materials);
-        res
+        self.apply(Action::Spawn);
     }
 }
 
-pub fn tetris_logic_setup(mut commands: Commands, time: Res<Time>) {
-    let logic = TetrisLogic::new();
-    commands.insert_resource(Ticker::new(&time, logic.get_interval()));
+/// Garbage rows kept on the board in Dig mode, leaving short boards room to play
+fn dig_rows(game: &Game) -> usize {
+    DIG_ROWS.min(game.visible_height() / 2)
+}
+
+pub fn tetris_logic_setup(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<GameConfig>,
+    settings: Res<Settings>,
+) {
+    let logic = TetrisLogic::new(
+        config.mode,
+        &config.mode.rules(&config.rules),
+        &settings.handling,
+        config.seed.unwrap_or_else(random),
+    );
+    commands.insert_resource(Ticker::new(&time, logic.game().gravity_interval()));
     commands.insert_resource(logic);
 }
 
+/// Picks up handling changed in the settings opened from the pause menu
+pub fn tetris_logic_handling(mut logic: ResMut<TetrisLogic>, settings: Res<Settings>) {
+    // Handling isn't shown, so there's nothing to sync
+    logic
+        .bypass_change_detection()
+        .set_handling(&settings.handling);
+}
+
 pub fn tetris_logic_update(
-    mut difficulty: ResMut<NextState<Difficulty>>,
-    mut score: ResMut<NextState<Score>>,
     mut logic: ResMut<TetrisLogic>,
-    mut board: ResMut<TetrisBoard>,
-    keyboard: Res<ButtonInput<KeyCode>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    controls: Res<ControlState>,
     mut ticker: ResMut<Ticker>,
-    mut game_over_state: ResMut<NextState<GameOver>>,
-    mut in_game_state: ResMut<NextState<InGameState>>,
     time: Res<Time>,
     // #[cfg(debug_assertions)] console: Res<ConsoleUiState>,
 ) {
+    logic.as_mut().advance(time.delta_secs());
+    if logic.finished() {
+        return;
+    }
+
     for _ in 0..ticker.as_mut().ticks(&time) {
-        logic.as_mut().tick(
-            &mut board,
-            &mut materials,
-            &mut difficulty,
-            &mut score,
-            &mut ticker,
-            &mut in_game_state,
-            &mut game_over_state,
-        );
+        logic.as_mut().tick();
     }
 
     #[cfg(debug_assertions)]
     // if !console.open() {
-    logic
-        .as_mut()
-        .update(board.as_mut(), &keyboard, materials.as_mut());
+    logic.as_mut().update(&controls, time.delta_secs());
     // }
 
     #[cfg(not(debug_assertions))]
-    logic
-        .as_mut()
-        .update(board.as_mut(), &keyboard, materials.as_mut());
+    logic.as_mut().update(&controls, time.delta_secs());
+
+    logic.as_mut().update_lock(time.delta_secs());
+    logic.as_mut().update_spawn(&controls, time.delta_secs());
+
+    ticker.set_interval(logic.game().gravity_interval());
+}
+
+/// Shows the game on the board and in the game states, whether it is being played or replayed
+#[allow(clippy::too_many_arguments)]
+pub fn tetris_logic_sync(
+    current_difficulty: Res<State<Difficulty>>,
+    mut difficulty: ResMut<NextState<Difficulty>>,
+    current_score: Res<State<Score>>,
+    mut score: ResMut<NextState<Score>>,
+    logic: Res<TetrisLogic>,
+    mut board: ResMut<TetrisBoard>,
+    settings: Res<Settings>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut game_over_state: ResMut<NextState<GameOver>>,
+    mut in_game_state: ResMut<NextState<InGameState>>,
+) {
+    if !logic.is_changed() {
+        return;
+    }
+
+    let game = logic.game();
+    board.sync(game, settings.visuals.show_ghost, &mut materials);
+
+    if game.is_over() {
+        in_game_state.set(InGameState::Paused);
+        game_over_state.set(GameOver::GameOver);
+    } else if logic.finished() {
+        in_game_state.set(InGameState::Paused);
+        game_over_state.set(GameOver::Finished);
+    }
+    if current_score.score != game.score() {
+        score.set(Score {
+            score: game.score(),
+        });
+    }
+    if current_difficulty.difficulty != game.lines() {
+        difficulty.set(Difficulty {
+            difficulty: game.lines(),
+        });
+    }
 }
 
 pub fn tetris_logic_shutdown(mut commands: Commands) {
     commands.remove_resource::<TetrisLogic>();
     commands.remove_resource::<Ticker>();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use t_tetris_engine::{Colors, Tetrominos};
+
+    /// Board cells, queue and held tetromino - what an undo should restore
+    type State = (Vec<Vec<Colors>>, Vec<Tetrominos>, Option<Tetrominos>);
+
+    fn zen() -> TetrisLogic {
+        let mode = GameMode::Zen;
+        TetrisLogic::new(
+            mode,
+            &mode.rules(&Rules::default()),
+            &Handling::default(),
+            1,
+        )
+    }
+
+    fn state(logic: &TetrisLogic) -> State {
+        let game = logic.game();
+        (
+            game.board().cells().clone(),
+            game.queue().iter().copied().collect(),
+            game.held().copied(),
+        )
+    }
+
+    /// Hard drops the tetromino in play and spawns the next one
+    fn place(logic: &mut TetrisLogic) {
+        logic.apply(Action::HardDrop);
+        while logic.game().piece().is_none() {
+            logic.apply(Action::Spawn);
+        }
+    }
+
+    /// Undoes and records it the way pressing undo does
+    fn press_undo(logic: &mut TetrisLogic) {
+        logic
+            .replay
+            .record((logic.elapsed * 1000.0) as u32, ReplayAction::Undo);
+        logic.undo();
+    }
+
+    #[test]
+    fn snapshots_are_taken_as_each_tetromino_spawns() {
+        let mut logic = zen();
+        assert!(logic.history.is_empty());
+
+        logic.apply(Action::Gravity);
+        assert_eq!(logic.history.len(), 1);
+        logic.apply(Action::Hold);
+        assert_eq!(logic.history.len(), 1);
+
+        for placed in 1..=3 {
+            place(&mut logic);
+            assert_eq!(logic.history.len(), placed + 1);
+        }
+    }
+
+    #[test]
+    fn undo_restores_board_queue_and_hold() {
+        let mut logic = zen();
+        logic.apply(Action::Gravity);
+        place(&mut logic);
+        let before = state(&logic);
+
+        logic.apply(Action::Hold);
+        place(&mut logic);
+        assert_ne!(state(&logic), before);
+
+        logic.undo();
+        assert_eq!(state(&logic), before);
+        assert!(logic.game().held().is_none());
+    }
+
+    #[test]
+    fn history_is_trimmed_to_max_undo() {
+        let mut logic = zen();
+        logic.apply(Action::Gravity);
+        for _ in 0..MAX_UNDO + 20 {
+            place(&mut logic);
+        }
+        assert_eq!(logic.history.len(), MAX_UNDO + 1);
+
+        for _ in 0..MAX_UNDO {
+            logic.undo();
+        }
+        let oldest = state(&logic);
+        logic.undo();
+        assert_eq!(state(&logic), oldest);
+        assert_eq!(logic.history.len(), 1);
+    }
+
+    #[test]
+    fn undo_after_a_top_out_restores_the_board_before_it() {
+        let mut logic = zen();
+        logic.apply(Action::Gravity);
+        let empty = state(&logic).0;
+
+        let mut before = state(&logic);
+        for _ in 0..100 {
+            before = state(&logic);
+            place(&mut logic);
+            if state(&logic).0 == empty {
+                break;
+            }
+        }
+        assert_eq!(state(&logic).0, empty, "never topped out");
+        assert_ne!(before.0, empty);
+
+        logic.undo();
+        assert_eq!(state(&logic), before);
+        assert!(!logic.game().is_over());
+    }
+
+    #[test]
+    fn recorded_undos_replay_the_same_game() {
+        let mut logic = zen();
+        logic.apply(Action::Gravity);
+        for _ in 0..3 {
+            place(&mut logic);
+        }
+        press_undo(&mut logic);
+        logic.apply(Action::Hold);
+        place(&mut logic);
+        press_undo(&mut logic);
+        press_undo(&mut logic);
+        place(&mut logic);
+
+        let mut replayed = zen();
+        for event in &logic.replay().events {
+            replayed.play(event);
+        }
+        assert_eq!(state(&replayed), state(&logic));
+        assert_eq!(replayed.history.len(), logic.history.len());
+    }
+
+    #[test]
+    fn unchanged_handling_keeps_the_das_charge() {
+        let mut logic = zen();
+        let handling = Handling::default();
+        logic.auto_shift.update(false, false, true, true, 0.0);
+        logic
+            .auto_shift
+            .update(false, false, true, false, handling.das * 0.75);
+
+        logic.set_handling(&handling);
+        assert!(logic
+            .auto_shift
+            .update(false, false, true, false, handling.das * 0.5)
+            .is_some());
+
+        logic.set_handling(&Handling {
+            das: handling.das * 2.0,
+            ..handling
+        });
+        assert!(logic
+            .auto_shift
+            .update(false, false, true, false, handling.das * 1.5)
+            .is_none());
+    }
+}
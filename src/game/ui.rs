@@ -1,7 +1,18 @@
-use crate::game::ui_setup::{DifficultyLabel, InfoLabel, ScoreLabel};
+use crate::game::config::GameMode;
+use crate::game::replay::ReplayPlayback;
+use crate::game::tetris_board::cell_color;
+use crate::game::tetris_logic::TetrisLogic;
+use crate::game::ui_setup::{
+    DifficultyLabel, HoldCell, InfoLabel, InstructionsLabel, PreviewCell, ScoreLabel,
+};
 use crate::game::{Difficulty, GameOver, InGameState, Score};
+use crate::settings::controls::Control;
+use crate::settings::Settings;
+use crate::util::{format_duration, format_precise_duration};
 use bevy::prelude::*;
+use t_tetris_engine::{Colors, Tetrominos};
 
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 pub fn update_labels(
     mut score_label: Query<
         &mut TextSpan,
@@ -31,20 +42,125 @@ pub fn update_labels(
     difficulty: Res<State<Difficulty>>,
     game_over: Res<State<GameOver>>,
     in_game: Res<State<InGameState>>,
+    logic: Res<TetrisLogic>,
+    playback: Option<Res<ReplayPlayback>>,
+    settings: Res<Settings>,
 ) {
     **score_label.single_mut() = format!("Score: {}", score.score);
-    **difficulty_label.single_mut() = format!("Difficulty: {}", difficulty.difficulty + 1);
+    **difficulty_label.single_mut() = match logic.mode() {
+        GameMode::Endless => format!("Difficulty: {}", difficulty.difficulty + 1),
+        GameMode::Sprint(goal) => format!("Lines: {}/{goal}", difficulty.difficulty),
+        GameMode::Marathon(goal) => {
+            let lines = match goal {
+                Some(goal) => format!("{}/{goal}", difficulty.difficulty),
+                None => difficulty.difficulty.to_string(),
+            };
+            format!("Level: {}\nLines: {lines}", logic.game().level())
+        }
+        GameMode::Zen => format!("Lines: {}", difficulty.difficulty),
+        GameMode::Dig(goal) => format!(
+            "Garbage: {}/{goal}\nTime: {}",
+            logic.game().garbage_cleared(),
+            format_precise_duration(logic.elapsed())
+        ),
+        GameMode::Survival => format!(
+            "Lines: {}\nTime: {}",
+            difficulty.difficulty,
+            format_duration(logic.elapsed())
+        ),
+        GameMode::Ultra(_) => format!(
+            "Time: {}",
+            format_precise_duration(logic.time_left().unwrap_or_default())
+        ),
+    };
 
-    let playing_text = if matches!(game_over.get(), GameOver::GameOver) {
-        "Game Over"
-    } else {
-        "Playing"
+    let playing_text = match game_over.get() {
+        GameOver::NotOver => "Playing",
+        GameOver::GameOver => "Game Over",
+        GameOver::Finished => "Finished",
     };
-    let state_text = if matches!(in_game.get(), InGameState::Paused) {
-        "\n[Paused, Restart : R, Main Menu : Return]"
+    let state_text = if let Some(playback) = playback {
+        format!("\n{}", playback.status())
+    } else if matches!(in_game.get(), InGameState::Paused) {
+        format!(
+            "\n[Paused, Restart : {}, Settings : S, Main Menu : Return]\nSeed: {}",
+            settings.controls.key_names(Control::Restart),
+            logic.game().seed()
+        )
     } else {
-        ""
+        String::new()
     };
 
     **info_label.single_mut() = format!("{playing_text}{state_text}");
 }
+
+/// Colour of cell `(x, y)` (top row first) of a mini board showing `piece` in its spawn
+/// orientation
+pub fn mini_board_color(piece: Option<&Tetrominos>, x: usize, y: usize) -> Colors {
+    let Some(piece) = piece else {
+        return Colors::Empty;
+    };
+
+    let shape = &piece.get_shape()[0];
+    let top = shape
+        .iter()
+        .position(|row| row.iter().any(|filled| *filled))
+        .unwrap_or(0);
+
+    if shape.get(top + y).is_some_and(|row| row[x]) {
+        piece.get_color()
+    } else {
+        Colors::Empty
+    }
+}
+
+pub fn update_previews(
+    mut cells: Query<(&PreviewCell, &mut BackgroundColor)>,
+    logic: Res<TetrisLogic>,
+) {
+    if !logic.is_changed() {
+        return;
+    }
+
+    for (cell, mut background) in cells.iter_mut() {
+        let color = cell_color(mini_board_color(
+            logic.game().queue().get(cell.slot),
+            cell.x,
+            cell.y,
+        ));
+        if background.0 != color {
+            background.0 = color;
+        }
+    }
+}
+
+/// Shows the current key bindings after they are changed from the pause menu
+pub fn update_instructions(
+    mut label: Query<&mut Text, With<InstructionsLabel>>,
+    settings: Res<Settings>,
+) {
+    for mut text in &mut label {
+        **text = settings.controls.instructions();
+    }
+}
+
+/// Colour of held piece cells while hold is unavailable
+const HOLD_UNAVAILABLE: Color = Color::srgb(0.4, 0.4, 0.4);
+
+pub fn update_hold(mut cells: Query<(&HoldCell, &mut BackgroundColor)>, logic: Res<TetrisLogic>) {
+    if !logic.is_changed() {
+        return;
+    }
+
+    for (cell, mut background) in cells.iter_mut() {
+        let game = logic.game();
+        let color = match mini_board_color(game.held(), cell.x, cell.y) {
+            Colors::Empty => cell_color(Colors::Empty),
+            _ if !game.can_hold() => HOLD_UNAVAILABLE,
+            color => cell_color(color),
+        };
+        if background.0 != color {
+            background.0 = color;
+        }
+    }
+}
@@ -1,7 +1,11 @@
-use crate::game::{OnGameScreen, RATIO};
+use crate::game::tetris_board::{cell_color, shown_height};
+use crate::game::tetris_logic::TetrisLogic;
+use crate::game::OnGameScreen;
 use crate::loading::GlobalFont;
+use crate::settings::Settings;
 use bevy::prelude::*;
 use bevy::window::WindowResized;
+use t_tetris_engine::{Colors, Game};
 
 #[derive(Component)]
 pub struct Board;
@@ -18,8 +22,65 @@ pub struct DifficultyLabel;
 #[derive(Component)]
 pub struct InfoLabel;
 
-pub fn get_target_and_sidebar_width(width: f32, height: f32) -> (f32, f32) {
-    let target_width = height * RATIO;
+/// Keys bound to each control, rewritten when they are rebound
+#[derive(Component)]
+pub struct InstructionsLabel;
+
+/// Label for information only some modes show, e.g. the Sprint timer
+#[derive(Component)]
+pub struct ModeLabel;
+
+/// Width and height of the boards used to preview a single tetromino
+pub const MINI_BOARD_WIDTH: usize = 4;
+pub const MINI_BOARD_HEIGHT: usize = 2;
+
+#[derive(Component)]
+pub struct PreviewCell {
+    pub slot: usize,
+    pub x: usize,
+    pub y: usize,
+}
+
+#[derive(Component)]
+pub struct HoldCell {
+    pub x: usize,
+    pub y: usize,
+}
+
+/// Spawns a grid of `MINI_BOARD_WIDTH` x `MINI_BOARD_HEIGHT` cells, tagging each cell (top row
+/// first) with the component returned by `cell`
+pub fn spawn_mini_board<C: Component>(parent: &mut ChildBuilder, cell: impl Fn(usize, usize) -> C) {
+    parent
+        .spawn(Node {
+            width: Val::Percent(40.),
+            aspect_ratio: Some(MINI_BOARD_WIDTH as f32 / MINI_BOARD_HEIGHT as f32),
+            margin: UiRect::all(Val::Px(5.)),
+            display: Display::Grid,
+            grid_template_columns: RepeatedGridTrack::flex(MINI_BOARD_WIDTH as u16, 1.0),
+            grid_template_rows: RepeatedGridTrack::flex(MINI_BOARD_HEIGHT as u16, 1.0),
+            ..default()
+        })
+        .with_children(|parent| {
+            for y in 0..MINI_BOARD_HEIGHT {
+                for x in 0..MINI_BOARD_WIDTH {
+                    parent.spawn((
+                        Node::default(),
+                        BackgroundColor(cell_color(Colors::Empty)),
+                        cell(x, y),
+                    ));
+                }
+            }
+        });
+}
+
+/// Most of the window's width the board can take up, leaving room for the sidebars when the
+/// board is much wider than it is tall
+const MAX_BOARD_FRACTION: f32 = 0.6;
+
+/// Width of the board and of each sidebar in a `width` x `height` window
+pub fn get_target_and_sidebar_width(width: f32, height: f32, game: &Game) -> (f32, f32) {
+    let ratio = game.board().width() as f32 / shown_height(game);
+    let target_width = (height * ratio).min(width * MAX_BOARD_FRACTION);
     let sidebar_width = (width - target_width) / 2.0;
     (target_width, sidebar_width)
 }
@@ -28,12 +89,14 @@ pub fn ui_resize_handler(
     mut resize_events: EventReader<WindowResized>,
     mut sidebars: Query<&mut Node, (With<SideBar>, Without<Board>)>,
     mut board: Query<&mut Node, (With<Board>, Without<SideBar>)>,
+    logic: Res<TetrisLogic>,
 ) {
     let Some(e) = resize_events.read().last() else {
         return;
     };
 
-    let (target_width, sidebar_width) = get_target_and_sidebar_width(e.width, e.height);
+    let (target_width, sidebar_width) =
+        get_target_and_sidebar_width(e.width, e.height, logic.game());
 
     for mut s in sidebars.iter_mut() {
         s.width = Val::Px(sidebar_width);
@@ -42,11 +105,17 @@ pub fn ui_resize_handler(
     board.single_mut().width = Val::Px(target_width);
 }
 
-pub fn ui_setup(mut commands: Commands, font: Res<GlobalFont>, window: Query<&Window>) {
+pub fn ui_setup(
+    mut commands: Commands,
+    font: Res<GlobalFont>,
+    logic: Res<TetrisLogic>,
+    settings: Res<Settings>,
+    window: Query<&Window>,
+) {
     let window = window.single();
 
     let (target_width, sidebar_width) =
-        get_target_and_sidebar_width(window.width(), window.height());
+        get_target_and_sidebar_width(window.width(), window.height(), logic.game());
 
     // root node
     commands
@@ -69,7 +138,7 @@ pub fn ui_setup(mut commands: Commands, font: Res<GlobalFont>, window: Query<&Wi
                         width: Val::Px(sidebar_width),
                         ..default()
                     },
-                    BackgroundColor(Color::srgb(0.65, 0.65, 0.65).into()),
+                    BackgroundColor(Color::srgb(0.65, 0.65, 0.65)),
                     SideBar,
                 ))
                 .with_children(|parent| {
@@ -98,6 +167,22 @@ pub fn ui_setup(mut commands: Commands, font: Res<GlobalFont>, window: Query<&Wi
                                 ScoreLabel,
                             ));
 
+                            parent.spawn((
+                                TextSpan("Hold".to_string()),
+                                TextFont {
+                                    font: font.get(),
+                                    font_size: 30.0,
+                                    ..default()
+                                },
+                                Node {
+                                    margin: UiRect::top(Val::Px(20.)),
+                                    ..default()
+                                },
+                                Label,
+                            ));
+
+                            spawn_mini_board(parent, |x, y| HoldCell { x, y });
+
                             parent.spawn((
                                 TextSpan("Difficulty: 1".to_string()),
                                 TextFont {
@@ -115,6 +200,21 @@ pub fn ui_setup(mut commands: Commands, font: Res<GlobalFont>, window: Query<&Wi
                                 Label,
                                 DifficultyLabel,
                             ));
+
+                            parent.spawn((
+                                TextSpan::default(),
+                                TextFont {
+                                    font: font.get(),
+                                    font_size: 25.0,
+                                    ..default()
+                                },
+                                Node {
+                                    margin: UiRect::all(Val::Px(5.)),
+                                    ..default()
+                                },
+                                Label,
+                                ModeLabel,
+                            ));
                         });
                 });
 
@@ -162,7 +262,7 @@ pub fn ui_setup(mut commands: Commands, font: Res<GlobalFont>, window: Query<&Wi
                         width: Val::Px(sidebar_width),
                         ..default()
                     },
-                    BackgroundColor(Color::srgb(0.65, 0.65, 0.65).into()),
+                    BackgroundColor(Color::srgb(0.65, 0.65, 0.65)),
                     SideBar,
                 ))
                 .with_children(|parent| {
@@ -176,6 +276,21 @@ pub fn ui_setup(mut commands: Commands, font: Res<GlobalFont>, window: Query<&Wi
                             ..default()
                         })
                         .with_children(|parent| {
+                            // next queue
+                            parent.spawn((
+                                TextSpan("Next".to_string()),
+                                TextFont {
+                                    font: font.get(),
+                                    font_size: 30.0,
+                                    ..default()
+                                },
+                                Label,
+                            ));
+
+                            for slot in 0..logic.game().queue().len() {
+                                spawn_mini_board(parent, |x, y| PreviewCell { slot, x, y });
+                            }
+
                             // text
                             parent.spawn((
                                 TextSpan("Playing".to_string()),
@@ -196,7 +311,7 @@ pub fn ui_setup(mut commands: Commands, font: Res<GlobalFont>, window: Query<&Wi
                             ));
 
                             parent.spawn((
-                                Text(include_str!("instructions.txt").to_string()),
+                                Text(settings.controls.instructions()),
                                 TextFont {
                                     font: font.get(),
                                     font_size: 30.0,
@@ -210,6 +325,7 @@ pub fn ui_setup(mut commands: Commands, font: Res<GlobalFont>, window: Query<&Wi
                                 // not button/list item text, this is necessary
                                 // for accessibility to treat the text accordingly.
                                 Label,
+                                InstructionsLabel,
                             ));
                         });
                 });
@@ -1,6 +1,7 @@
 use bevy::app::{App, Plugin};
 use bevy::prelude::*;
 
+use crate::settings::Settings;
 use crate::util::despawn_screen;
 use crate::GameState;
 
@@ -33,10 +34,6 @@ impl GlobalFont {
     pub fn get(&self) -> Handle<Font> {
         self.font.as_ref().unwrap().clone()
     }
-
-    pub fn get_ref(&self) -> &Handle<Font> {
-        self.font.as_ref().unwrap()
-    }
 }
 
 fn loading_setup(
@@ -45,7 +42,8 @@ fn loading_setup(
     mut font: ResMut<GlobalFont>,
     mut game_state: ResMut<NextState<GameState>>,
 ) {
-    commands.spawn(Camera2d::default());
+    commands.spawn(Camera2d);
+    commands.insert_resource(Settings::load());
     font.set(asset_server.load("fonts/FiraSans-Bold.ttf"));
     game_state.set(GameState::Menu);
 }
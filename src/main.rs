@@ -2,15 +2,18 @@ mod custom_functions;
 mod game;
 mod loading;
 mod menu;
+mod settings;
 mod util;
 
 // use crate::custom_functions::dev_console_environment;
+use crate::game::config::GameConfig;
 use crate::game::GamePlugin;
 use crate::loading::LoadingPlugin;
 use crate::menu::MenuPlugin;
+use crate::settings::SettingsPlugin;
+use crate::util::button_colors;
 use bevy::log::LogPlugin;
 use bevy::prelude::*;
-use bevy::window::{PresentMode, WindowMode};
 // use bevy_dev_console::prelude::ConsoleLogPlugin;
 // use bevy_dev_console::DevConsolePlugin;
 
@@ -21,14 +24,58 @@ pub enum GameState {
     Menu,
     Game,
     ReloadGame,
+    Replay,
+}
+
+/// Command line options
+#[derive(Default)]
+struct Args {
+    seed: Option<u64>,
+}
+
+impl Args {
+    fn parse() -> Result<Args, String> {
+        let mut args = Args::default();
+        let mut iter = std::env::args().skip(1);
+
+        while let Some(arg) = iter.next() {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+
+            match name.as_str() {
+                "--seed" => {
+                    let value = value
+                        .or_else(|| iter.next())
+                        .ok_or("--seed requires a value")?;
+                    args.seed = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("Invalid seed '{value}'"))?,
+                    );
+                }
+                _ => return Err(format!("Unknown argument '{name}'")),
+            }
+        }
+
+        Ok(args)
+    }
 }
 
 fn main() {
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}\nUsage: t_tetris [--seed <seed>]");
+            std::process::exit(2);
+        }
+    };
+
     let default_plugins = DefaultPlugins
         .set(WindowPlugin {
             primary_window: Some(Window {
                 title: "Tetris".to_string(),
-                present_mode: PresentMode::Immediate,
                 resolution: default(),
                 ..default()
             }),
@@ -58,22 +105,12 @@ fn main() {
         // #[cfg(debug_assertions)]
         // DevConsolePlugin,
     ))
-    .insert_resource(ClearColor(Color::WHITE))
+    .insert_resource(GameConfig {
+        seed: args.seed,
+        ..default()
+    })
     .init_state::<GameState>()
-    .add_systems(Update, fullscreen)
-    .add_plugins((LoadingPlugin, MenuPlugin, GamePlugin))
+    .add_systems(Update, button_colors)
+    .add_plugins((LoadingPlugin, SettingsPlugin, MenuPlugin, GamePlugin))
     .run();
 }
-
-fn fullscreen(keyboard_input: Res<ButtonInput<KeyCode>>, mut window: Query<&mut Window>) {
-    if keyboard_input.just_pressed(KeyCode::F11) {
-        let mut window = window.single_mut();
-        match &window.mode {
-            WindowMode::Fullscreen(_) => {
-                window.mode = WindowMode::Windowed;
-                window.resolution = default();
-            }
-            _ => window.mode = WindowMode::Fullscreen(MonitorSelection::Current),
-        };
-    }
-}
@@ -1,54 +1,218 @@
 use bevy::app::{App, Plugin};
 use bevy::prelude::*;
+use t_tetris_engine::replay::Replay;
 
+use crate::game::config::{
+    GameConfig, GameMode, BOARD_HEIGHTS, BOARD_WIDTHS, MAX_PREVIEW_COUNT, MIN_PREVIEW_COUNT,
+};
+use crate::game::high_scores::HighScores;
+use crate::game::replay::{saved_replays, SelectedReplay};
 use crate::loading::GlobalFont;
-use crate::util::despawn_screen;
+use crate::settings::SettingsButton;
+use crate::util::{despawn_screen, format_date, format_duration, spawn_button, NORMAL_BUTTON};
 use crate::GameState;
 
 #[derive(Component)]
 struct OnMenuScreen;
 
+#[derive(Component)]
+struct OnReplaysScreen;
+
+#[derive(Component)]
+struct OnHighScoresScreen;
+
+/// Screen of the menu being shown
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, SubStates)]
+#[source(GameState = GameState::Menu)]
+enum MenuState {
+    #[default]
+    Main,
+    Replays,
+    HighScores,
+}
+
 pub struct MenuPlugin;
 
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Menu), menu_setup)
-            .add_systems(Update, (play_button).run_if(in_state(GameState::Menu)))
-            .add_systems(OnExit(GameState::Menu), despawn_screen::<OnMenuScreen>);
+        app.add_sub_state::<MenuState>()
+            .add_systems(OnEnter(MenuState::Main), menu_setup)
+            .add_systems(OnEnter(MenuState::Replays), replays_setup)
+            .add_systems(OnEnter(MenuState::HighScores), high_scores_setup)
+            .add_systems(Update, menu_button.run_if(in_state(GameState::Menu)))
+            .add_systems(
+                Update,
+                (play_button, option_button).run_if(in_state(MenuState::Main)),
+            )
+            .add_systems(Update, replay_button.run_if(in_state(MenuState::Replays)))
+            .add_systems(OnExit(MenuState::Main), despawn_screen::<OnMenuScreen>)
+            .add_systems(
+                OnExit(MenuState::HighScores),
+                despawn_screen::<OnHighScoresScreen>,
+            )
+            .add_systems(
+                OnExit(MenuState::Replays),
+                despawn_screen::<OnReplaysScreen>,
+            );
     }
 }
 
 #[derive(Component)]
 struct PlayButton;
 
-const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
-const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
+/// Button going to another screen of the menu
+#[derive(Component)]
+struct MenuButton(MenuState);
+
+/// Button playing back the replay it holds
+#[derive(Component)]
+struct ReplayButton(Option<Replay>);
+
+/// Number of the most recent replays listed
+const MAX_LISTED_REPLAYS: usize = 8;
+
+/// Choices for how often garbage holes change column
+const GARBAGE_MESSINESS: [f32; 5] = [0.0, 0.1, 0.3, 0.6, 1.0];
+
+/// Button cycling through the values of a `GameConfig` field
+#[derive(Component, Clone, Copy)]
+enum OptionButton {
+    Mode,
+    Width,
+    Height,
+    Rotation,
+    Generator,
+    Scoring,
+    Gravity,
+    Preview,
+    LockResets,
+    Delays,
+    Messiness,
+}
+
+impl OptionButton {
+    fn cycle(&self, config: &mut GameConfig) {
+        match self {
+            OptionButton::Mode => config.mode = config.mode.next(),
+            OptionButton::Width => config.rules.width = next_of(&BOARD_WIDTHS, config.rules.width),
+            OptionButton::Height => {
+                config.rules.height = next_of(&BOARD_HEIGHTS, config.rules.height)
+            }
+            OptionButton::Rotation => {
+                config.rules.rotation_system = config.rules.rotation_system.next()
+            }
+            OptionButton::Generator => config.rules.generator = config.rules.generator.next(),
+            OptionButton::Scoring => config.rules.scoring = config.rules.scoring.next(),
+            OptionButton::Gravity => config.rules.gravity = config.rules.gravity.next(),
+            OptionButton::Preview => {
+                config.rules.preview_count = if config.rules.preview_count >= MAX_PREVIEW_COUNT {
+                    MIN_PREVIEW_COUNT
+                } else {
+                    config.rules.preview_count + 1
+                }
+            }
+            OptionButton::Delays => config.rules.spawn_delays = config.rules.spawn_delays.next(),
+            OptionButton::Messiness => {
+                let index = GARBAGE_MESSINESS
+                    .iter()
+                    .position(|messiness| *messiness == config.rules.garbage_messiness)
+                    .map_or(0, |index| index + 1);
+                config.rules.garbage_messiness = GARBAGE_MESSINESS[index % GARBAGE_MESSINESS.len()]
+            }
+            OptionButton::LockResets => {
+                config.rules.max_lock_resets = match config.rules.max_lock_resets {
+                    Some(0) => Some(15),
+                    Some(_) => None,
+                    None => Some(0),
+                }
+            }
+        }
+    }
+
+    fn text(&self, config: &GameConfig) -> String {
+        match self {
+            OptionButton::Mode => format!("Mode: {}", config.mode.name()),
+            OptionButton::Width => format!("Board width: {}", config.rules.width),
+            OptionButton::Height => format!("Board height: {}", config.rules.height),
+            OptionButton::Rotation => format!("Rotation: {}", config.rules.rotation_system.name()),
+            OptionButton::Generator => format!("Randomizer: {}", config.rules.generator.name()),
+            OptionButton::Scoring => format!("Scoring: {}", config.rules.scoring.name()),
+            OptionButton::Gravity => format!("Gravity: {}", config.rules.gravity.name()),
+            OptionButton::Preview => format!("Next pieces: {}", config.rules.preview_count),
+            OptionButton::LockResets => match config.rules.max_lock_resets {
+                Some(max) => format!("Lock resets: {max}"),
+                None => "Lock resets: Infinite".to_string(),
+            },
+            OptionButton::Delays => format!("Spawn delays: {}", config.rules.spawn_delays.name()),
+            OptionButton::Messiness => format!(
+                "Garbage messiness: {}%",
+                (config.rules.garbage_messiness * 100.0).round()
+            ),
+        }
+    }
+}
+
+/// Value after `current` in `values`, wrapping around
+fn next_of(values: &[usize], current: usize) -> usize {
+    let index = values
+        .iter()
+        .position(|value| *value == current)
+        .map_or(0, |index| index + 1);
+    values[index % values.len()]
+}
 
 fn play_button(
-    mut interaction_query: Query<
-        (&Interaction, &mut BackgroundColor, &mut BorderColor),
-        (Changed<Interaction>, With<Button>, With<PlayButton>),
-    >,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<PlayButton>)>,
     mut game_state: ResMut<NextState<GameState>>,
 ) {
-    for (interaction, mut color, mut border_color) in &mut interaction_query {
-        match *interaction {
-            Interaction::Pressed => {
-                game_state.set(GameState::Game);
-            }
-            Interaction::Hovered => {
-                *color = HOVERED_BUTTON.into();
-                border_color.0 = Color::WHITE;
-            }
-            Interaction::None => {
-                *color = NORMAL_BUTTON.into();
-                border_color.0 = Color::BLACK;
+    for interaction in &interaction_query {
+        if matches!(interaction, Interaction::Pressed) {
+            game_state.set(GameState::Game);
+        }
+    }
+}
+
+fn menu_button(
+    interaction_query: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut menu_state: ResMut<NextState<MenuState>>,
+) {
+    for (interaction, button) in &interaction_query {
+        if matches!(interaction, Interaction::Pressed) {
+            menu_state.set(button.0);
+        }
+    }
+}
+
+fn replay_button(
+    mut commands: Commands,
+    mut interaction_query: Query<(&Interaction, &mut ReplayButton), Changed<Interaction>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, mut button) in &mut interaction_query {
+        if matches!(interaction, Interaction::Pressed) {
+            if let Some(replay) = button.0.take() {
+                commands.insert_resource(SelectedReplay(replay));
+                game_state.set(GameState::Replay);
             }
         }
     }
 }
 
-fn menu_setup(mut commands: Commands, font: Res<GlobalFont>) {
+fn option_button(
+    interaction_query: Query<(&Interaction, &OptionButton, &Children), Changed<Interaction>>,
+    mut text_query: Query<&mut Text>,
+    mut config: ResMut<GameConfig>,
+) {
+    for (interaction, option, children) in &interaction_query {
+        if matches!(interaction, Interaction::Pressed) {
+            option.cycle(&mut config);
+            let mut text = text_query.get_mut(children[0]).unwrap();
+            **text = option.text(&config);
+        }
+    }
+}
+
+fn menu_setup(mut commands: Commands, font: Res<GlobalFont>, config: Res<GameConfig>) {
     // Root node
     commands
         .spawn((
@@ -98,7 +262,7 @@ fn menu_setup(mut commands: Commands, font: Res<GlobalFont>) {
                         ..default()
                     },
                     BorderColor(Color::BLACK),
-                    BackgroundColor(NORMAL_BUTTON.into()),
+                    BackgroundColor(NORMAL_BUTTON),
                     PlayButton,
                 ))
                 .with_children(|parent| {
@@ -112,5 +276,156 @@ fn menu_setup(mut commands: Commands, font: Res<GlobalFont>) {
                         TextColor(Color::srgb(0.9, 0.9, 0.9)),
                     ));
                 });
+
+            spawn_button(parent, "Replays", &font, MenuButton(MenuState::Replays));
+            spawn_button(
+                parent,
+                "High Scores",
+                &font,
+                MenuButton(MenuState::HighScores),
+            );
+            spawn_button(parent, "Settings", &font, SettingsButton);
+
+            // Rule options
+            parent
+                .spawn(Node {
+                    flex_wrap: FlexWrap::Wrap,
+                    justify_content: JustifyContent::Center,
+                    max_width: Val::Px(700.0),
+                    column_gap: Val::Px(20.0),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for option in [
+                        OptionButton::Mode,
+                        OptionButton::Width,
+                        OptionButton::Height,
+                        OptionButton::Rotation,
+                        OptionButton::Generator,
+                        OptionButton::Scoring,
+                        OptionButton::Gravity,
+                        OptionButton::Preview,
+                        OptionButton::LockResets,
+                        OptionButton::Delays,
+                        OptionButton::Messiness,
+                    ] {
+                        spawn_button(parent, &option.text(&config), &font, option);
+                    }
+                });
+        });
+}
+
+fn replays_setup(mut commands: Commands, font: Res<GlobalFont>) {
+    let replays = saved_replays(MAX_LISTED_REPLAYS);
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::SpaceEvenly,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            OnReplaysScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text("Replays".to_string()),
+                TextFont {
+                    font: font.get(),
+                    font_size: 40.0,
+                    ..default()
+                },
+                TextColor(Color::BLACK),
+            ));
+
+            if replays.is_empty() {
+                parent.spawn((
+                    Text("No saved replays".to_string()),
+                    TextFont {
+                        font: font.get(),
+                        font_size: 30.0,
+                        ..default()
+                    },
+                    TextColor(Color::BLACK),
+                ));
+            }
+
+            for (saved_at, replay) in replays {
+                let date = saved_at.map_or("Unknown date".to_string(), format_date);
+                let text = format!(
+                    "{} - {date} ({})",
+                    replay.mode,
+                    format_duration(replay.duration() as f32 / 1000.0)
+                );
+                spawn_button(parent, &text, &font, ReplayButton(Some(replay)));
+            }
+
+            spawn_button(parent, "Back", &font, MenuButton(MenuState::Main));
+        });
+}
+
+fn high_scores_setup(mut commands: Commands, font: Res<GlobalFont>, high_scores: Res<HighScores>) {
+    let text_font = |font_size| TextFont {
+        font: font.get(),
+        font_size,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::SpaceEvenly,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            OnHighScoresScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text("High Scores".to_string()),
+                text_font(40.0),
+                TextColor(Color::BLACK),
+            ));
+
+            // Tables side by side, wrapping onto more rows as modes are added
+            parent
+                .spawn(Node {
+                    flex_wrap: FlexWrap::Wrap,
+                    justify_content: JustifyContent::Center,
+                    column_gap: Val::Px(40.0),
+                    row_gap: Val::Px(20.0),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for mode in GameMode::ALL.into_iter().filter(GameMode::ranks_by_score) {
+                        let scores = high_scores.get(mode);
+                        let mut text = format!("{}\n", mode.name());
+                        if scores.is_empty() {
+                            text += "No scores yet";
+                        }
+                        for (i, score) in scores.iter().enumerate() {
+                            text += &format!(
+                                "\n{}. {} - {} ({} lines, level {}, {}, {})",
+                                i + 1,
+                                score.name,
+                                score.score,
+                                score.lines,
+                                score.level,
+                                format_duration(score.duration),
+                                format_date(score.date)
+                            );
+                        }
+
+                        parent.spawn((Text(text), text_font(20.0), TextColor(Color::BLACK)));
+                    }
+                });
+
+            spawn_button(parent, "Back", &font, MenuButton(MenuState::Main));
         });
 }
@@ -0,0 +1,364 @@
+use bevy::input::gamepad::Gamepad;
+use bevy::input::ButtonInput;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::settings::Settings;
+
+/// Most keys that can be bound to one control
+pub const MAX_BINDINGS: usize = 3;
+
+/// Everything the player can do with a key
+#[derive(Serialize, Deserialize, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
+pub enum Control {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateClockwise,
+    RotateAnticlockwise,
+    Rotate180,
+    Hold,
+    Undo,
+    Pause,
+    Restart,
+}
+
+impl Control {
+    pub const ALL: [Control; 11] = [
+        Control::MoveLeft,
+        Control::MoveRight,
+        Control::SoftDrop,
+        Control::HardDrop,
+        Control::RotateClockwise,
+        Control::RotateAnticlockwise,
+        Control::Rotate180,
+        Control::Hold,
+        Control::Undo,
+        Control::Pause,
+        Control::Restart,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Control::MoveLeft => "Move left",
+            Control::MoveRight => "Move right",
+            Control::SoftDrop => "Soft drop",
+            Control::HardDrop => "Hard drop",
+            Control::RotateClockwise => "Rotate clockwise",
+            Control::RotateAnticlockwise => "Rotate anticlockwise",
+            Control::Rotate180 => "Rotate 180",
+            Control::Hold => "Hold",
+            Control::Undo => "Undo (Zen)",
+            Control::Pause => "Pause/Play",
+            Control::Restart => "Restart (paused)",
+        }
+    }
+
+    fn default_keys(&self) -> Vec<KeyCode> {
+        match self {
+            Control::MoveLeft => vec![KeyCode::ArrowLeft],
+            Control::MoveRight => vec![KeyCode::ArrowRight],
+            Control::SoftDrop => vec![KeyCode::ArrowDown],
+            Control::HardDrop => vec![KeyCode::ArrowUp],
+            Control::RotateClockwise => vec![KeyCode::KeyE],
+            Control::RotateAnticlockwise => vec![KeyCode::KeyQ],
+            Control::Rotate180 => vec![KeyCode::KeyW],
+            Control::Hold => vec![KeyCode::KeyC],
+            Control::Undo => vec![KeyCode::KeyZ],
+            Control::Pause => vec![KeyCode::Escape],
+            Control::Restart => vec![KeyCode::KeyR],
+        }
+    }
+}
+
+/// Keys bound to each control. Controls missing from the settings file keep their default
+/// keys.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(
+    from = "BTreeMap<Control, Vec<KeyCode>>",
+    into = "BTreeMap<Control, Vec<KeyCode>>"
+)]
+pub struct Controls {
+    bindings: BTreeMap<Control, Vec<KeyCode>>,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Controls {
+            bindings: Control::ALL
+                .iter()
+                .map(|control| (*control, control.default_keys()))
+                .collect(),
+        }
+    }
+}
+
+impl From<BTreeMap<Control, Vec<KeyCode>>> for Controls {
+    fn from(bindings: BTreeMap<Control, Vec<KeyCode>>) -> Self {
+        let mut controls = Controls::default();
+        controls.bindings.extend(bindings);
+        controls
+    }
+}
+
+impl From<Controls> for BTreeMap<Control, Vec<KeyCode>> {
+    fn from(controls: Controls) -> Self {
+        controls.bindings
+    }
+}
+
+impl Controls {
+    pub fn keys(&self, control: Control) -> &[KeyCode] {
+        self.bindings
+            .get(&control)
+            .map_or(&[], |keys| keys.as_slice())
+    }
+
+    pub fn pressed(&self, keyboard: &ButtonInput<KeyCode>, control: Control) -> bool {
+        keyboard.any_pressed(self.keys(control).iter().copied())
+    }
+
+    pub fn just_pressed(&self, keyboard: &ButtonInput<KeyCode>, control: Control) -> bool {
+        keyboard.any_just_pressed(self.keys(control).iter().copied())
+    }
+
+    /// Control other than `control` already using `key`
+    pub fn conflict(&self, control: Control, key: KeyCode) -> Option<Control> {
+        self.bindings
+            .iter()
+            .find(|(other, keys)| **other != control && keys.contains(&key))
+            .map(|(other, _)| *other)
+    }
+
+    /// Adds `key` to `control`, replacing its oldest key if it already has the most allowed
+    pub fn bind(&mut self, control: Control, key: KeyCode) {
+        let keys = self.bindings.entry(control).or_default();
+        if keys.contains(&key) {
+            return;
+        }
+        if keys.len() >= MAX_BINDINGS {
+            keys.remove(0);
+        }
+        keys.push(key);
+    }
+
+    pub fn clear(&mut self, control: Control) {
+        self.bindings.insert(control, Vec::new());
+    }
+
+    /// Keys bound to `control` for showing to the player
+    pub fn key_names(&self, control: Control) -> String {
+        let keys = self.keys(control);
+        if keys.is_empty() {
+            return "Unbound".to_string();
+        }
+
+        keys.iter()
+            .map(|key| key_name(*key))
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+
+    /// Instructions shown beside the board
+    pub fn instructions(&self) -> String {
+        let mut text = "Instructions:".to_string();
+        for control in Control::ALL {
+            text += &format!("\n    - {} : {}", control.name(), self.key_names(control));
+        }
+        text
+    }
+}
+
+/// Short name of `key` for showing to the player
+pub fn key_name(key: KeyCode) -> String {
+    let name = format!("{key:?}");
+
+    match key {
+        KeyCode::ArrowLeft => "←".to_string(),
+        KeyCode::ArrowRight => "→".to_string(),
+        KeyCode::ArrowUp => "↑".to_string(),
+        KeyCode::ArrowDown => "↓".to_string(),
+        KeyCode::Escape => "Esc".to_string(),
+        KeyCode::Enter => "Return".to_string(),
+        _ => name
+            .strip_prefix("Key")
+            .or_else(|| name.strip_prefix("Digit"))
+            .unwrap_or(&name)
+            .to_string(),
+    }
+}
+
+/// Controls held this frame on the keyboard or any controller
+#[derive(Resource, Default)]
+pub struct ControlState {
+    pressed: HashSet<Control>,
+    just_pressed: HashSet<Control>,
+}
+
+impl ControlState {
+    pub fn pressed(&self, control: Control) -> bool {
+        self.pressed.contains(&control)
+    }
+
+    pub fn just_pressed(&self, control: Control) -> bool {
+        self.just_pressed.contains(&control)
+    }
+}
+
+/// Reads the keyboard and controllers into `ControlState`. Only reads input resources and
+/// components, so controllers can be simulated by sending gamepad events.
+pub fn read_controls(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    settings: Res<Settings>,
+    mut state: ResMut<ControlState>,
+) {
+    let controls = &settings.controls;
+    let pad = &settings.pad;
+
+    let pressed: HashSet<Control> = Control::ALL
+        .into_iter()
+        .filter(|control| {
+            controls.pressed(&keyboard, *control)
+                || gamepads
+                    .iter()
+                    .any(|gamepad| pad.pressed(gamepad, *control))
+        })
+        .collect();
+    // Presses that started this frame, including the stick being pushed past the deadzone
+    let just_pressed = Control::ALL
+        .into_iter()
+        .filter(|control| {
+            controls.just_pressed(&keyboard, *control)
+                || gamepads
+                    .iter()
+                    .any(|gamepad| pad.just_pressed(gamepad, *control))
+                || (pressed.contains(control) && !state.pressed.contains(control))
+        })
+        .collect();
+
+    state.pressed = pressed;
+    state.just_pressed = just_pressed;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::input::gamepad::{
+        GamepadAxis, GamepadButton, RawGamepadAxisChangedEvent, RawGamepadButtonChangedEvent,
+        RawGamepadEvent,
+    };
+    use bevy::input::{InputPlugin, InputSystem};
+    use t_tetris_engine::handling::{AutoShift, Shift};
+
+    /// Seconds between simulated frames
+    const FRAME: f32 = 1.0 / 60.0;
+
+    /// A headless app reading controls from a single connected controller
+    fn app() -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, InputPlugin))
+            .init_resource::<Settings>()
+            .init_resource::<ControlState>()
+            .add_systems(PreUpdate, read_controls.after(InputSystem));
+        let gamepad = app.world_mut().spawn(Gamepad::default()).id();
+        app.update();
+
+        (app, gamepad)
+    }
+
+    /// Sends `events` from the controller and runs a frame
+    fn frame(app: &mut App, events: impl IntoIterator<Item = RawGamepadEvent>) -> &ControlState {
+        app.world_mut()
+            .resource_mut::<Events<RawGamepadEvent>>()
+            .send_batch(events);
+        app.update();
+        app.world().resource::<ControlState>()
+    }
+
+    fn button(gamepad: Entity, button: GamepadButton, value: f32) -> RawGamepadEvent {
+        RawGamepadEvent::Button(RawGamepadButtonChangedEvent::new(gamepad, button, value))
+    }
+
+    fn axis(gamepad: Entity, axis: GamepadAxis, value: f32) -> RawGamepadEvent {
+        RawGamepadEvent::Axis(RawGamepadAxisChangedEvent::new(gamepad, axis, value))
+    }
+
+    #[test]
+    fn buttons_press_controls() {
+        let (mut app, gamepad) = app();
+
+        let state = frame(&mut app, [button(gamepad, GamepadButton::South, 1.0)]);
+        assert!(state.pressed(Control::RotateClockwise));
+        assert!(state.just_pressed(Control::RotateClockwise));
+        assert!(!state.pressed(Control::RotateAnticlockwise));
+
+        let state = frame(&mut app, []);
+        assert!(state.pressed(Control::RotateClockwise));
+        assert!(!state.just_pressed(Control::RotateClockwise));
+
+        let state = frame(&mut app, [button(gamepad, GamepadButton::South, 0.0)]);
+        assert!(!state.pressed(Control::RotateClockwise));
+    }
+
+    #[test]
+    fn stick_counts_as_dpad_past_deadzone() {
+        let (mut app, gamepad) = app();
+
+        let state = frame(&mut app, [axis(gamepad, GamepadAxis::LeftStickX, -0.3)]);
+        assert!(!state.pressed(Control::MoveLeft));
+
+        let state = frame(&mut app, [axis(gamepad, GamepadAxis::LeftStickX, -0.9)]);
+        assert!(state.pressed(Control::MoveLeft));
+        assert!(state.just_pressed(Control::MoveLeft));
+
+        let state = frame(&mut app, []);
+        assert!(state.pressed(Control::MoveLeft));
+        assert!(!state.just_pressed(Control::MoveLeft));
+
+        let state = frame(&mut app, [axis(gamepad, GamepadAxis::LeftStickX, 0.0)]);
+        assert!(!state.pressed(Control::MoveLeft));
+    }
+
+    /// Holds left for `frames` frames after sending `press`, returning the cells moved on
+    /// each frame
+    fn hold_left(press: impl Fn(Entity) -> RawGamepadEvent, frames: usize) -> Vec<usize> {
+        let (mut app, gamepad) = app();
+        let handling = Settings::default().handling;
+        let mut auto_shift = AutoShift::new(handling.das, handling.arr);
+
+        (0..frames)
+            .map(|i| {
+                let events = (i == 0).then(|| press(gamepad));
+                let state = frame(&mut app, events);
+                match auto_shift.update(
+                    state.pressed(Control::MoveLeft),
+                    state.just_pressed(Control::MoveLeft),
+                    state.pressed(Control::MoveRight),
+                    state.just_pressed(Control::MoveRight),
+                    FRAME,
+                ) {
+                    Some((Shift::Left, moves)) => moves,
+                    Some((Shift::Right, _)) => panic!("moved right while holding left"),
+                    None => 0,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn held_dpad_and_stick_repeat_after_das() {
+        let das_frames = (Settings::default().handling.das / FRAME) as usize;
+
+        for moves in [
+            hold_left(|gamepad| button(gamepad, GamepadButton::DPadLeft, 1.0), 60),
+            hold_left(|gamepad| axis(gamepad, GamepadAxis::LeftStickX, -1.0), 60),
+        ] {
+            assert_eq!(moves[0], 1);
+            assert!(moves[1..das_frames].iter().all(|moves| *moves == 0));
+            assert!(moves[das_frames..].iter().sum::<usize>() > 5);
+        }
+    }
+}
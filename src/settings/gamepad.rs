@@ -0,0 +1,205 @@
+use bevy::input::gamepad::{Gamepad, GamepadButton};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::controls::Control;
+use crate::util::{HOVERED_BUTTON, NORMAL_BUTTON};
+
+const NINTENDO_VENDOR_ID: u16 = 0x057e;
+
+/// Which face buttons rotate which way
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Eq, PartialEq, Debug)]
+pub enum PadLayout {
+    /// Picks the layout from the controller's vendor
+    #[default]
+    Auto,
+    /// Xbox and PlayStation pads, bottom button rotates clockwise
+    Standard,
+    /// Nintendo pads, right button (A) rotates clockwise
+    Nintendo,
+}
+
+impl PadLayout {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PadLayout::Auto => "Auto",
+            PadLayout::Standard => "Xbox/PlayStation",
+            PadLayout::Nintendo => "Nintendo",
+        }
+    }
+
+    pub fn next(&self) -> PadLayout {
+        match self {
+            PadLayout::Auto => PadLayout::Standard,
+            PadLayout::Standard => PadLayout::Nintendo,
+            PadLayout::Nintendo => PadLayout::Auto,
+        }
+    }
+
+    /// Layout used for `gamepad`, resolving `Auto`
+    fn resolve(&self, gamepad: &Gamepad) -> PadLayout {
+        match self {
+            PadLayout::Auto if gamepad.vendor_id() == Some(NINTENDO_VENDOR_ID) => {
+                PadLayout::Nintendo
+            }
+            PadLayout::Auto => PadLayout::Standard,
+            layout => *layout,
+        }
+    }
+
+    /// Buttons bound to `control`
+    fn buttons(&self, control: Control) -> &'static [GamepadButton] {
+        use GamepadButton::*;
+
+        match (control, self) {
+            (Control::MoveLeft, _) => &[DPadLeft],
+            (Control::MoveRight, _) => &[DPadRight],
+            (Control::SoftDrop, _) => &[DPadDown],
+            (Control::HardDrop, _) => &[DPadUp],
+            (Control::RotateClockwise, PadLayout::Nintendo) => &[East],
+            (Control::RotateClockwise, _) => &[South],
+            (Control::RotateAnticlockwise, PadLayout::Nintendo) => &[South],
+            (Control::RotateAnticlockwise, _) => &[East],
+            (Control::Rotate180, _) => &[North],
+            (Control::Hold, _) => &[West, LeftTrigger, RightTrigger],
+            (Control::Undo, _) => &[LeftTrigger2],
+            (Control::Pause, _) => &[Start],
+            (Control::Restart, _) => &[Select],
+        }
+    }
+}
+
+/// Controller preferences
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Pad {
+    pub layout: PadLayout,
+    /// How far the left stick has to be pushed before it counts as the D-pad, from 0 to 1
+    pub deadzone: f32,
+}
+
+impl Default for Pad {
+    fn default() -> Self {
+        Pad {
+            layout: PadLayout::default(),
+            deadzone: 0.5,
+        }
+    }
+}
+
+impl Pad {
+    /// Whether `control` is held on `gamepad`, counting the left stick as the D-pad
+    pub fn pressed(&self, gamepad: &Gamepad, control: Control) -> bool {
+        let layout = self.layout.resolve(gamepad);
+        let stick = gamepad.left_stick();
+
+        gamepad.any_pressed(layout.buttons(control).iter().copied())
+            || match control {
+                Control::MoveLeft => stick.x < -self.deadzone,
+                Control::MoveRight => stick.x > self.deadzone,
+                Control::SoftDrop => stick.y < -self.deadzone,
+                _ => false,
+            }
+    }
+
+    /// Whether a button bound to `control` was pressed on `gamepad` this frame. The stick is
+    /// left to `ControlState`, which compares it with the previous frame.
+    pub fn just_pressed(&self, gamepad: &Gamepad, control: Control) -> bool {
+        let layout = self.layout.resolve(gamepad);
+        gamepad.any_just_pressed(layout.buttons(control).iter().copied())
+    }
+}
+
+/// Button selected with a controller in the menus
+#[derive(Resource, Default)]
+pub struct PadFocus {
+    focused: Option<Entity>,
+    /// Button pressed last frame, released this frame as it isn't under the mouse
+    pressed: Option<Entity>,
+}
+
+/// Moves between the buttons on the top screen with the D-pad and presses them with the
+/// bottom face button
+pub fn gamepad_navigation(
+    gamepads: Query<&Gamepad>,
+    mut focus: ResMut<PadFocus>,
+    mut buttons: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &mut Interaction,
+            &mut BackgroundColor,
+            &mut BorderColor,
+        ),
+        With<Button>,
+    >,
+    parents: Query<&Parent>,
+    z_indices: Query<&GlobalZIndex>,
+) {
+    if let Some(entity) = focus.pressed.take() {
+        if let Ok((_, _, mut interaction, _, _)) = buttons.get_mut(entity) {
+            interaction.set_if_neq(Interaction::None);
+        }
+    }
+
+    let just_pressed = |button| gamepads.iter().any(|pad| pad.just_pressed(button));
+    let step = if just_pressed(GamepadButton::DPadDown) || just_pressed(GamepadButton::DPadRight) {
+        1
+    } else if just_pressed(GamepadButton::DPadUp) || just_pressed(GamepadButton::DPadLeft) {
+        -1
+    } else {
+        0
+    };
+    let confirm = just_pressed(GamepadButton::South);
+    if step == 0 && !confirm {
+        return;
+    }
+
+    // Only buttons on the screen drawn on top, e.g. the settings over the pause menu
+    let layer = |entity: Entity| {
+        let root = parents.iter_ancestors(entity).last().unwrap_or(entity);
+        z_indices.get(root).map_or(0, |z| z.0)
+    };
+    let mut order: Vec<(Entity, Vec3, i32)> = buttons
+        .iter()
+        .map(|(entity, transform, ..)| (entity, transform.translation(), layer(entity)))
+        .collect();
+    let top = order.iter().map(|(.., layer)| *layer).max();
+    order.retain(|(.., layer)| Some(*layer) == top);
+    order.sort_by(|(_, a, _), (_, b, _)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+    if order.is_empty() {
+        return;
+    }
+
+    let current = focus
+        .focused
+        .and_then(|focused| order.iter().position(|(entity, ..)| *entity == focused));
+
+    if confirm {
+        if let Some(index) = current {
+            let entity = order[index].0;
+            if let Ok((_, _, mut interaction, _, _)) = buttons.get_mut(entity) {
+                *interaction = Interaction::Pressed;
+                focus.pressed = Some(entity);
+            }
+        }
+        return;
+    }
+
+    let next = match current {
+        Some(index) => (index as i32 + step).rem_euclid(order.len() as i32) as usize,
+        None => 0,
+    };
+    let next = order[next].0;
+
+    if let Some(previous) = focus.focused.replace(next) {
+        if let Ok((_, _, _, mut color, mut border)) = buttons.get_mut(previous) {
+            color.0 = NORMAL_BUTTON;
+            border.0 = Color::BLACK;
+        }
+    }
+    if let Ok((_, _, _, mut color, mut border)) = buttons.get_mut(next) {
+        color.0 = HOVERED_BUTTON;
+        border.0 = Color::WHITE;
+    }
+}
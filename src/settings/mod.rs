@@ -0,0 +1,656 @@
+use bevy::app::{App, Plugin};
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::ui::{FocusPolicy, UiSystem};
+use bevy::window::{PresentMode, WindowMode};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+pub mod controls;
+pub mod gamepad;
+
+use crate::loading::GlobalFont;
+use crate::settings::controls::{key_name, read_controls, Control, ControlState, Controls};
+use crate::settings::gamepad::{gamepad_navigation, Pad, PadFocus};
+use crate::util::{config_dir, despawn_screen, spawn_button};
+
+/// How the tetromino responds to held keys
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Handling {
+    /// Delayed Auto Shift - seconds a direction is held before it starts repeating
+    pub das: f32,
+    /// Auto Repeat Rate - seconds between repeated moves, 0 to move instantly
+    pub arr: f32,
+    /// How many times faster than gravity soft drop moves, infinite to drop instantly
+    pub soft_drop_factor: f32,
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Handling {
+            das: 0.167,
+            arr: 0.033,
+            soft_drop_factor: 20.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Eq, PartialEq, Debug)]
+pub enum Background {
+    #[default]
+    White,
+    LightGrey,
+    Grey,
+}
+
+impl Background {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Background::White => "White",
+            Background::LightGrey => "Light grey",
+            Background::Grey => "Grey",
+        }
+    }
+
+    pub fn next(&self) -> Background {
+        match self {
+            Background::White => Background::LightGrey,
+            Background::LightGrey => Background::Grey,
+            Background::Grey => Background::White,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Background::White => Color::WHITE,
+            Background::LightGrey => Color::srgb(0.85, 0.85, 0.85),
+            Background::Grey => Color::srgb(0.7, 0.7, 0.7),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Visuals {
+    /// Whether to show where the current tetromino will land
+    pub show_ghost: bool,
+    pub background: Background,
+}
+
+impl Default for Visuals {
+    fn default() -> Self {
+        Visuals {
+            show_ghost: true,
+            background: Background::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Audio {
+    /// Volume from 0 to 1
+    pub volume: f32,
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Audio { volume: 0.5 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct Display {
+    pub fullscreen: bool,
+    pub vsync: bool,
+}
+
+/// Player preferences kept between sessions in `settings.toml`
+#[derive(Resource, Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct Settings {
+    pub controls: Controls,
+    pub pad: Pad,
+    pub handling: Handling,
+    pub visuals: Visuals,
+    pub audio: Audio,
+    pub display: Display,
+}
+
+impl Settings {
+    fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("settings.toml"))
+    }
+
+    /// Loads the saved settings, using the defaults if they are missing or invalid
+    pub fn load() -> Settings {
+        let Some(path) = Self::path() else {
+            warn!("No config directory to load settings from, using defaults");
+            return Settings::default();
+        };
+
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                warn!(
+                    "Failed to read settings from {}, using defaults: {e}",
+                    path.display()
+                );
+                return Settings::default();
+            }
+        };
+
+        toml::from_str(&text).unwrap_or_else(|e| {
+            warn!(
+                "Invalid settings in {}, using defaults: {e}",
+                path.display()
+            );
+            Settings::default()
+        })
+    }
+
+    pub fn save(&self) {
+        let Some(path) = Self::path() else {
+            warn!("No config directory to save settings in");
+            return;
+        };
+
+        let result = toml::to_string_pretty(self)
+            .map_err(std::io::Error::other)
+            .and_then(|text| {
+                path.parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|_| fs::write(&path, text))
+            });
+        if let Err(e) = result {
+            warn!("Failed to save settings to {}: {e}", path.display());
+        }
+    }
+}
+
+/// Whether the settings screen is shown over the current screen
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
+pub enum SettingsState {
+    #[default]
+    Closed,
+    Open,
+    Controls,
+}
+
+#[derive(Component)]
+struct OnSettingsScreen;
+
+/// Button opening the settings screen
+#[derive(Component)]
+pub struct SettingsButton;
+
+#[derive(Component)]
+struct OnControlsScreen;
+
+/// Button going to another page of the settings, or closing them with `Closed`
+#[derive(Component)]
+struct SettingsPageButton(SettingsState);
+
+/// Button waiting for a key to bind to its control
+#[derive(Component)]
+struct RebindButton(Control);
+
+/// Button removing every key bound to its control
+#[derive(Component)]
+struct ClearBindingButton(Control);
+
+#[derive(Component)]
+struct RebindStatus;
+
+/// Control waiting for a key to be pressed
+#[derive(Resource, Default)]
+struct Rebinding(Option<Control>);
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<SettingsState>()
+            .init_resource::<ControlState>()
+            .init_resource::<PadFocus>()
+            .add_systems(
+                PreUpdate,
+                (
+                    read_controls
+                        .after(InputSystem)
+                        .run_if(resource_exists::<Settings>),
+                    gamepad_navigation.after(UiSystem::Focus),
+                ),
+            )
+            .add_systems(OnEnter(SettingsState::Open), settings_setup)
+            .add_systems(
+                OnExit(SettingsState::Open),
+                (despawn_screen::<OnSettingsScreen>, save_settings),
+            )
+            .add_systems(OnEnter(SettingsState::Controls), controls_setup)
+            .add_systems(
+                OnExit(SettingsState::Controls),
+                (despawn_screen::<OnControlsScreen>, save_settings),
+            )
+            .add_systems(
+                Update,
+                (
+                    open_settings_button,
+                    fullscreen.run_if(resource_exists::<Settings>),
+                    apply_settings.run_if(resource_exists_and_changed::<Settings>),
+                ),
+            )
+            .add_systems(Update, setting_button.run_if(in_state(SettingsState::Open)))
+            .add_systems(
+                Update,
+                settings_page_button.run_if(not(in_state(SettingsState::Closed))),
+            )
+            .add_systems(
+                Update,
+                (rebind_button, clear_binding_button, rebind_key)
+                    .chain()
+                    .run_if(in_state(SettingsState::Controls)),
+            );
+    }
+}
+
+const DAS_OPTIONS: [f32; 8] = [0.05, 0.083, 0.1, 0.117, 0.133, 0.167, 0.2, 0.25];
+const ARR_OPTIONS: [f32; 6] = [0.0, 0.017, 0.033, 0.05, 0.083, 0.1];
+const SOFT_DROP_OPTIONS: [f32; 5] = [5.0, 10.0, 20.0, 40.0, f32::INFINITY];
+const DEADZONE_OPTIONS: [f32; 5] = [0.2, 0.3, 0.4, 0.5, 0.6];
+const VOLUME_STEP: f32 = 0.1;
+
+/// The option after `current`, wrapping round to the first
+fn next_option(options: &[f32], current: f32) -> f32 {
+    options
+        .iter()
+        .copied()
+        .find(|option| *option > current + 0.0001)
+        .unwrap_or(options[0])
+}
+
+/// Button cycling through the values of a `Settings` field
+#[derive(Component, Clone, Copy)]
+enum SettingButton {
+    Das,
+    Arr,
+    SoftDrop,
+    Ghost,
+    Background,
+    Volume,
+    Fullscreen,
+    Vsync,
+    PadLayout,
+    Deadzone,
+}
+
+impl SettingButton {
+    const ALL: [SettingButton; 10] = [
+        SettingButton::Das,
+        SettingButton::Arr,
+        SettingButton::SoftDrop,
+        SettingButton::Ghost,
+        SettingButton::Background,
+        SettingButton::Volume,
+        SettingButton::Fullscreen,
+        SettingButton::Vsync,
+        SettingButton::PadLayout,
+        SettingButton::Deadzone,
+    ];
+
+    fn cycle(&self, settings: &mut Settings) {
+        let handling = &mut settings.handling;
+        match self {
+            SettingButton::Das => handling.das = next_option(&DAS_OPTIONS, handling.das),
+            SettingButton::Arr => handling.arr = next_option(&ARR_OPTIONS, handling.arr),
+            SettingButton::SoftDrop => {
+                handling.soft_drop_factor =
+                    next_option(&SOFT_DROP_OPTIONS, handling.soft_drop_factor)
+            }
+            SettingButton::Ghost => settings.visuals.show_ghost = !settings.visuals.show_ghost,
+            SettingButton::Background => {
+                settings.visuals.background = settings.visuals.background.next()
+            }
+            SettingButton::Volume => {
+                let volume = ((settings.audio.volume + VOLUME_STEP) * 10.0).round() / 10.0;
+                settings.audio.volume = if volume > 1.0 { 0.0 } else { volume };
+            }
+            SettingButton::Fullscreen => settings.display.fullscreen = !settings.display.fullscreen,
+            SettingButton::Vsync => settings.display.vsync = !settings.display.vsync,
+            SettingButton::PadLayout => settings.pad.layout = settings.pad.layout.next(),
+            SettingButton::Deadzone => {
+                settings.pad.deadzone = next_option(&DEADZONE_OPTIONS, settings.pad.deadzone)
+            }
+        }
+    }
+
+    fn text(&self, settings: &Settings) -> String {
+        let on_off = |on: bool| if on { "On" } else { "Off" };
+        let handling = &settings.handling;
+        match self {
+            SettingButton::Das => format!("DAS: {}ms", (handling.das * 1000.0).round()),
+            SettingButton::Arr => format!("ARR: {}ms", (handling.arr * 1000.0).round()),
+            SettingButton::SoftDrop if handling.soft_drop_factor.is_infinite() => {
+                "Soft drop: Instant".to_string()
+            }
+            SettingButton::SoftDrop => format!("Soft drop: {}x", handling.soft_drop_factor),
+            SettingButton::Ghost => format!("Ghost: {}", on_off(settings.visuals.show_ghost)),
+            SettingButton::Background => {
+                format!("Background: {}", settings.visuals.background.name())
+            }
+            SettingButton::Volume => {
+                format!("Volume: {}%", (settings.audio.volume * 100.0).round())
+            }
+            SettingButton::Fullscreen => format!(
+                "Display: {}",
+                if settings.display.fullscreen {
+                    "Fullscreen"
+                } else {
+                    "Windowed"
+                }
+            ),
+            SettingButton::Vsync => format!("VSync: {}", on_off(settings.display.vsync)),
+            SettingButton::PadLayout => format!("Pad layout: {}", settings.pad.layout.name()),
+            SettingButton::Deadzone => {
+                format!(
+                    "Stick deadzone: {}%",
+                    (settings.pad.deadzone * 100.0).round()
+                )
+            }
+        }
+    }
+}
+
+fn open_settings_button(
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<SettingsButton>)>,
+    mut settings_state: ResMut<NextState<SettingsState>>,
+) {
+    for interaction in &interaction_query {
+        if matches!(interaction, Interaction::Pressed) {
+            settings_state.set(SettingsState::Open);
+        }
+    }
+}
+
+fn settings_page_button(
+    interaction_query: Query<(&Interaction, &SettingsPageButton), Changed<Interaction>>,
+    mut settings_state: ResMut<NextState<SettingsState>>,
+) {
+    for (interaction, button) in &interaction_query {
+        if matches!(interaction, Interaction::Pressed) {
+            settings_state.set(button.0);
+        }
+    }
+}
+
+fn setting_button(
+    interaction_query: Query<(&Interaction, &SettingButton, &Children), Changed<Interaction>>,
+    mut text_query: Query<&mut Text>,
+    mut settings: ResMut<Settings>,
+) {
+    for (interaction, setting, children) in &interaction_query {
+        if matches!(interaction, Interaction::Pressed) {
+            setting.cycle(&mut settings);
+            let mut text = text_query.get_mut(children[0]).unwrap();
+            **text = setting.text(&settings);
+        }
+    }
+}
+
+fn save_settings(settings: Res<Settings>) {
+    settings.save();
+}
+
+/// Applies the display, visual and audio settings whenever they change
+fn apply_settings(
+    settings: Res<Settings>,
+    mut window: Query<&mut Window>,
+    mut clear_color: ResMut<ClearColor>,
+    mut volume: ResMut<GlobalVolume>,
+) {
+    let mut window = window.single_mut();
+    let display = &settings.display;
+
+    match (&window.mode, display.fullscreen) {
+        (WindowMode::Windowed, true) => {
+            window.mode = WindowMode::Fullscreen(MonitorSelection::Current)
+        }
+        (WindowMode::Windowed, false) => {}
+        (_, true) => {}
+        (_, false) => {
+            window.mode = WindowMode::Windowed;
+            window.resolution = default();
+        }
+    }
+
+    let present_mode = if display.vsync {
+        PresentMode::AutoVsync
+    } else {
+        PresentMode::Immediate
+    };
+    if window.present_mode != present_mode {
+        window.present_mode = present_mode;
+    }
+
+    clear_color.0 = settings.visuals.background.color();
+    *volume = GlobalVolume::new(settings.audio.volume);
+}
+
+fn fullscreen(keyboard_input: Res<ButtonInput<KeyCode>>, mut settings: ResMut<Settings>) {
+    if keyboard_input.just_pressed(KeyCode::F11) {
+        settings.display.fullscreen = !settings.display.fullscreen;
+        settings.save();
+    }
+}
+
+fn settings_setup(mut commands: Commands, font: Res<GlobalFont>, settings: Res<Settings>) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::SpaceEvenly,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            FocusPolicy::Block,
+            GlobalZIndex(1),
+            OnSettingsScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text("Settings".to_string()),
+                TextFont {
+                    font: font.get(),
+                    font_size: 40.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+
+            parent
+                .spawn(Node {
+                    flex_wrap: FlexWrap::Wrap,
+                    justify_content: JustifyContent::Center,
+                    max_width: Val::Px(700.0),
+                    column_gap: Val::Px(20.0),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for setting in SettingButton::ALL {
+                        spawn_button(parent, &setting.text(&settings), &font, setting);
+                    }
+                });
+
+            spawn_button(
+                parent,
+                "Controls",
+                &font,
+                SettingsPageButton(SettingsState::Controls),
+            );
+            spawn_button(
+                parent,
+                "Back",
+                &font,
+                SettingsPageButton(SettingsState::Closed),
+            );
+        });
+}
+
+const REBIND_HINT: &str = "Click a control then press a key to bind it";
+
+fn rebind_text(controls: &Controls, control: Control) -> String {
+    format!("{}: {}", control.name(), controls.key_names(control))
+}
+
+/// Starts waiting for a key for the clicked control, or stops if it was already waiting
+fn rebind_button(
+    interaction_query: Query<(&Interaction, &RebindButton), Changed<Interaction>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut status: Query<&mut Text, With<RebindStatus>>,
+) {
+    for (interaction, button) in &interaction_query {
+        if matches!(interaction, Interaction::Pressed) {
+            rebinding.0 = if rebinding.0 == Some(button.0) {
+                **status.single_mut() = REBIND_HINT.to_string();
+                None
+            } else {
+                **status.single_mut() = format!("Press a key for {}", button.0.name());
+                Some(button.0)
+            };
+        }
+    }
+}
+
+fn clear_binding_button(
+    interaction_query: Query<(&Interaction, &ClearBindingButton), Changed<Interaction>>,
+    mut settings: ResMut<Settings>,
+    mut buttons: Query<(&RebindButton, &Children)>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (interaction, button) in &interaction_query {
+        if !matches!(interaction, Interaction::Pressed) {
+            continue;
+        }
+
+        settings.controls.clear(button.0);
+        for (rebind, children) in &mut buttons {
+            if rebind.0 == button.0 {
+                **text_query.get_mut(children[0]).unwrap() =
+                    rebind_text(&settings.controls, button.0);
+            }
+        }
+    }
+}
+
+/// Binds the first key pressed to the control waiting for one, refusing keys already used
+/// by another control
+fn rebind_key(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut settings: ResMut<Settings>,
+    buttons: Query<(&RebindButton, &Children)>,
+    mut text_query: Query<&mut Text>,
+    status: Query<Entity, With<RebindStatus>>,
+) {
+    let Some(control) = rebinding.0 else {
+        return;
+    };
+    let Some(key) = keyboard.get_just_pressed().next().copied() else {
+        return;
+    };
+    rebinding.0 = None;
+
+    let status = status.single();
+    if let Some(other) = settings.controls.conflict(control, key) {
+        **text_query.get_mut(status).unwrap() = format!(
+            "{} is already bound to {}, clear it first",
+            key_name(key),
+            other.name()
+        );
+        return;
+    }
+
+    settings.controls.bind(control, key);
+    **text_query.get_mut(status).unwrap() = REBIND_HINT.to_string();
+    for (rebind, children) in &buttons {
+        if rebind.0 == control {
+            **text_query.get_mut(children[0]).unwrap() = rebind_text(&settings.controls, control);
+        }
+    }
+}
+
+fn controls_setup(mut commands: Commands, font: Res<GlobalFont>, settings: Res<Settings>) {
+    commands.insert_resource(Rebinding::default());
+
+    let text_font = |font_size| TextFont {
+        font: font.get(),
+        font_size,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::SpaceEvenly,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            FocusPolicy::Block,
+            GlobalZIndex(1),
+            OnControlsScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text("Controls".to_string()),
+                text_font(40.0),
+                TextColor(Color::WHITE),
+            ));
+            parent.spawn((
+                Text(REBIND_HINT.to_string()),
+                text_font(25.0),
+                TextColor(Color::WHITE),
+                RebindStatus,
+            ));
+
+            parent
+                .spawn(Node {
+                    flex_wrap: FlexWrap::Wrap,
+                    justify_content: JustifyContent::Center,
+                    max_width: Val::Px(1100.0),
+                    column_gap: Val::Px(20.0),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for control in Control::ALL {
+                        parent.spawn(Node::default()).with_children(|parent| {
+                            spawn_button(
+                                parent,
+                                &rebind_text(&settings.controls, control),
+                                &font,
+                                RebindButton(control),
+                            );
+                            spawn_button(parent, "Clear", &font, ClearBindingButton(control));
+                        });
+                    }
+                });
+
+            spawn_button(
+                parent,
+                "Back",
+                &font,
+                SettingsPageButton(SettingsState::Open),
+            );
+        });
+}
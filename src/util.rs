@@ -1,7 +1,123 @@
+use crate::loading::GlobalFont;
 use bevy::prelude::*;
+use std::path::PathBuf;
+
+pub const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
+pub const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
+
+/// Highlights buttons while hovered or pressed
+#[allow(clippy::type_complexity)]
+pub fn button_colors(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &mut BorderColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, mut color, mut border_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed | Interaction::Hovered => {
+                *color = HOVERED_BUTTON.into();
+                border_color.0 = Color::WHITE;
+            }
+            Interaction::None => {
+                *color = NORMAL_BUTTON.into();
+                border_color.0 = Color::BLACK;
+            }
+        }
+    }
+}
 
 pub fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands: Commands) {
     for entity in &to_despawn {
         commands.entity(entity).despawn_recursive();
     }
 }
+
+/// Directory t_tetris stores its saved data in
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("t_tetris"))
+}
+
+/// Directory t_tetris stores its settings in
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("t_tetris"))
+}
+
+/// Formats seconds since the unix epoch as a `YYYY-MM-DD` date (UTC)
+pub fn format_date(unix: u64) -> String {
+    // Howard Hinnant's days to civil date algorithm
+    let days = (unix / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Formats seconds as `M:SS`
+pub fn format_duration(seconds: f32) -> String {
+    let seconds = seconds as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Formats seconds as `M:SS.mmm`
+pub fn format_precise_duration(seconds: f32) -> String {
+    let millis = (seconds * 1000.0).round() as u32;
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Spawns a button labelled `text` with `button` as its marker
+pub fn spawn_button(parent: &mut ChildBuilder, text: &str, font: &GlobalFont, button: impl Bundle) {
+    parent
+        .spawn((
+            Button,
+            Node {
+                min_width: Val::Px(300.0),
+                height: Val::Px(65.0),
+                border: UiRect::all(Val::Px(5.0)),
+                padding: UiRect::horizontal(Val::Px(15.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BorderColor(Color::BLACK),
+            BackgroundColor(NORMAL_BUTTON),
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text(text.to_string()),
+                TextFont {
+                    font: font.get(),
+                    font_size: 30.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.9, 0.9, 0.9)),
+            ));
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_date_gives_the_utc_day() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_399), "2000-02-28");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_700_000_000), "2023-11-14");
+        assert_eq!(format_date(4_107_542_400), "2100-03-01");
+    }
+}